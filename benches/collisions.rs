@@ -0,0 +1,162 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use keep_it_alive::components::{
+    BirdSpecies, Bullet, BulletState, Collider, Flyer, Height, HitPoints, Layers, Position, Width,
+};
+use keep_it_alive::resources::{Collision, SeededRng};
+use keep_it_alive::scoring::Score;
+use keep_it_alive::species::Species;
+use keep_it_alive::systems::{DetectCollisionsSystem, ShootBirdsSystem, UpdateSpatialGridSystem};
+use keep_it_alive::Config;
+use rand::prelude::*;
+use specs::prelude::*;
+use specs::shrev::{EventChannel, ReaderId};
+
+const ARENA_WIDTH: f32 = 1024.0;
+const ARENA_HEIGHT: f32 = 768.0;
+const SIZES: [usize; 3] = [100, 1000, 4000];
+// about as crowded as the screen gets in a long game
+const BIRDS_PER_SCREEN: usize = 100;
+
+/// A world with `count` birds and `count` bullets in flight, and the reader
+/// `ShootBirdsSystem` hears about hits on. The field grows with the count so it stays as
+/// crowded as a real game, where most bullets miss.
+fn build_world(count: usize) -> (World, ReaderId<Collision>) {
+    let screens = (count as f32 / BIRDS_PER_SCREEN as f32).max(1.0);
+    let field_width = ARENA_WIDTH * screens.sqrt();
+    let field_height = ARENA_HEIGHT * screens.sqrt();
+
+    let mut world = World::new();
+    System::setup(&mut UpdateSpatialGridSystem, &mut world);
+    System::setup(&mut DetectCollisionsSystem, &mut world);
+    let mut collisions = EventChannel::<Collision>::new();
+    let mut reader = collisions.register_reader();
+    world.insert(collisions);
+    System::setup(
+        &mut ShootBirdsSystem {
+            duration_since_start: 0,
+            collisions: &mut reader,
+            killed: &mut BitSet::new(),
+        },
+        &mut world,
+    );
+    world.register::<Bullet>();
+    world.register::<Width>();
+    world.register::<Height>();
+    world.insert(Config::new());
+    world.insert(SeededRng::new(1));
+    world.insert(Score::new());
+
+    let config = Config::new();
+    let mut rng = StdRng::seed_from_u64(2);
+    for _ in 0..count {
+        world
+            .create_entity()
+            .with(Position {
+                x: rng.gen_range(0.0, field_width),
+                y: rng.gen_range(0.0, field_height),
+            })
+            .with(Width::new(config.bird_width))
+            .with(Height::new(config.bird_height))
+            .with(Collider::circle(
+                config.bird_hit_distance,
+                Layers::BIRD,
+                Layers::NONE,
+            ))
+            .with(Flyer)
+            .with(BirdSpecies::new(Species::Pigeon))
+            .with(HitPoints::new(1))
+            .build();
+    }
+    for _ in 0..count {
+        let mut bullet_state = BulletState::new();
+        bullet_state.fire();
+        world
+            .create_entity()
+            .with(Position {
+                x: rng.gen_range(0.0, field_width),
+                y: rng.gen_range(0.0, field_height),
+            })
+            .with(Collider::circle(0.0, Layers::BULLET, Layers::BIRD))
+            .with(Bullet)
+            .with(bullet_state)
+            .build();
+    }
+    (world, reader)
+}
+
+/// What every bullet did before the grid: check itself against every bird in the world
+/// until it hits one.
+fn count_hits_brute_force(world: &World) -> usize {
+    let config = world.fetch::<Config>();
+    let position = world.read_storage::<Position>();
+    let bullet = world.read_storage::<Bullet>();
+    let flyer = world.read_storage::<Flyer>();
+    let mut hits = 0;
+
+    for (bullet_position, _bullet) in (&position, &bullet).join() {
+        for (flyer_position, _flyer) in (&position, &flyer).join() {
+            let x = flyer_position.x - bullet_position.x;
+            let y = flyer_position.y - bullet_position.y;
+            if (x * x + y * y).sqrt() < config.bird_hit_distance {
+                hits += 1;
+                break;
+            }
+        }
+    }
+    hits
+}
+
+fn shoot_birds(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("shoot_birds");
+    group.sample_size(20);
+
+    for count in SIZES.iter() {
+        // shooting deletes birds, so every run gets a fresh world
+        group.bench_with_input(
+            BenchmarkId::new("spatial_grid", count),
+            count,
+            |bench, count| {
+                bench.iter_batched(
+                    || build_world(*count),
+                    |(world, mut reader)| {
+                        UpdateSpatialGridSystem.run_now(&world);
+                        DetectCollisionsSystem.run_now(&world);
+                        ShootBirdsSystem {
+                            duration_since_start: 0,
+                            collisions: &mut reader,
+                            killed: &mut BitSet::new(),
+                        }
+                        .run_now(&world);
+                        world
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+
+        let (world, _reader) = build_world(*count);
+        group.bench_with_input(
+            BenchmarkId::new("brute_force", count),
+            count,
+            |bench, _count| bench.iter(|| count_hits_brute_force(&world)),
+        );
+    }
+    group.finish();
+}
+
+fn rebuild_grid(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("rebuild_spatial_grid");
+
+    for count in SIZES.iter() {
+        let (world, _reader) = build_world(*count);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            count,
+            |bench, _count| bench.iter(|| UpdateSpatialGridSystem.run_now(&world)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, shoot_birds, rebuild_grid);
+criterion_main!(benches);
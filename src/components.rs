@@ -1,5 +1,8 @@
-use ggez::graphics::Mesh;
-use specs::{Component, NullStorage, VecStorage};
+use super::species::Species;
+use ggez::graphics::Color;
+use specs::{Component, DenseVecStorage, Entity, NullStorage, VecStorage};
+use std::collections::HashMap;
+use std::ops::BitOr;
 
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
@@ -44,20 +47,64 @@ impl Width {
     }
 }
 
+/// What happens to an entity when it reaches the left or right edge of the arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeBehaviour {
+    /// Stops at the edge.
+    Clamp,
+    /// Turns around at the edge.
+    Bounce,
+    /// Comes back in on the other side once it is all the way off the screen.
+    Wrap,
+}
+
+/// Makes `KeepInBoundsSystem` hold the entity to the sides of the arena. Entities without
+/// it are free to leave.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct KeepInBounds(EdgeBehaviour);
+
+impl KeepInBounds {
+    pub fn new(edge_behaviour: EdgeBehaviour) -> KeepInBounds {
+        KeepInBounds(edge_behaviour)
+    }
+
+    pub fn get(&self) -> EdgeBehaviour {
+        self.0
+    }
+}
+
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
-pub struct ObjectMesh(Mesh);
+pub struct ObjectMesh(MeshType);
 
 impl ObjectMesh {
-    pub fn new(mesh: Mesh) -> ObjectMesh {
-        ObjectMesh(mesh)
+    pub fn new(mesh_type: MeshType) -> ObjectMesh {
+        ObjectMesh(mesh_type)
+    }
+
+    pub fn get(&self) -> MeshType {
+        self.0
     }
 
-    pub fn get(&self) -> &Mesh {
-        &self.0
+    pub fn set(&mut self, mesh_type: MeshType) {
+        self.0 = mesh_type;
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshType {
+    Egg,
+    Player,
+    Floor,
+    Bird(WingPose),
+    Bullet,
+    Poop,
+    PowerUp(PowerUp),
+    Grenade,
+    Explosion,
+}
+
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
 pub struct Velocity {
@@ -84,12 +131,22 @@ impl Drag {
     pub fn get(&self) -> f32 {
         self.0
     }
+
+    pub fn set(&mut self, drag: f32) {
+        self.0 = drag;
+    }
 }
 
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
 pub struct OnGround(bool);
 
+impl Default for OnGround {
+    fn default() -> OnGround {
+        OnGround::new()
+    }
+}
+
 impl OnGround {
     pub fn new() -> OnGround {
         OnGround(false)
@@ -108,6 +165,12 @@ impl OnGround {
 #[storage(VecStorage)]
 pub struct KeepAlive(bool);
 
+impl Default for KeepAlive {
+    fn default() -> KeepAlive {
+        KeepAlive::new()
+    }
+}
+
 impl KeepAlive {
     pub fn new() -> KeepAlive {
         KeepAlive(true)
@@ -134,31 +197,28 @@ pub struct Bullet;
 #[storage(NullStorage)]
 pub struct Player;
 
+/// On the egg after its carrier has been shot, until it is caught or hits the floor.
+#[derive(Default, Component, Debug)]
+#[storage(NullStorage)]
+pub struct Falling;
+
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
-pub struct Radius(f32);
+pub struct BulletState(CurrentBulletState);
 
-impl Radius {
-    pub fn new(radius: f32) -> Radius {
-        Radius(radius)
-    }
-
-    pub fn get(&self) -> f32 {
-        self.0
+impl Default for BulletState {
+    fn default() -> BulletState {
+        BulletState::new()
     }
 }
 
-#[derive(Component, Debug)]
-#[storage(VecStorage)]
-pub struct BulletState(CurrentBulletState);
-
 impl BulletState {
     pub fn new() -> BulletState {
         BulletState(CurrentBulletState::Ready)
     }
 
     pub fn get(&self) -> CurrentBulletState {
-        self.0.clone()
+        self.0
     }
 
     pub fn fire(&mut self) {
@@ -180,3 +240,554 @@ pub enum CurrentBulletState {
     Firing,
     Hit,
 }
+
+/// On the egg while a bird is flying off with it. The egg follows this bird around.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct CarriedBy(Entity);
+
+impl CarriedBy {
+    pub fn new(carrier: Entity) -> CarriedBy {
+        CarriedBy(carrier)
+    }
+
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// On the egg once a bird has grabbed it, so a lost game can be blamed on the right
+/// species. Stays after the egg is dropped and is replaced by the next bird to grab it.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct StolenBy(Species);
+
+impl StolenBy {
+    pub fn new(species: Species) -> StolenBy {
+        StolenBy(species)
+    }
+
+    pub fn get(&self) -> Species {
+        self.0
+    }
+}
+
+/// On the bird that has the egg, with the point off screen that it is escaping to.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct CarryingEgg {
+    pub escape_x: f32,
+    pub escape_y: f32,
+}
+
+#[derive(Default, Component, Debug)]
+#[storage(NullStorage)]
+pub struct Poop;
+
+/// When a bird is allowed to drop its next poop, in milliseconds since the game started.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct DelayPoopUntilAfter(u128);
+
+impl DelayPoopUntilAfter {
+    pub fn new(time: u128) -> DelayPoopUntilAfter {
+        DelayPoopUntilAfter(time)
+    }
+
+    pub fn get(&self) -> u128 {
+        self.0
+    }
+
+    pub fn set(&mut self, new_time: u128) {
+        self.0 = new_time;
+    }
+}
+
+/// On the player after being hit by poop. They can't move or shoot until this time, in
+/// milliseconds since the game started.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct StunnedUntil(u128);
+
+impl StunnedUntil {
+    pub fn new(time: u128) -> StunnedUntil {
+        StunnedUntil(time)
+    }
+
+    pub fn get(&self) -> u128 {
+        self.0
+    }
+}
+
+/// Deletes the entity once it runs out, in milliseconds since the game started.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct Lifetime {
+    started_at: u128,
+    ends_at: u128,
+}
+
+impl Lifetime {
+    pub fn new(started_at: u128, duration: u128) -> Lifetime {
+        Lifetime {
+            started_at,
+            ends_at: started_at + duration,
+        }
+    }
+
+    pub fn is_over(&self, now: u128) -> bool {
+        self.ends_at <= now
+    }
+
+    /// How much of the lifetime has gone, from 0 when it starts to 1 when it is over.
+    pub fn progress(&self, now: u128) -> f32 {
+        if self.is_over(now) {
+            return 1.0;
+        }
+        now.saturating_sub(self.started_at) as f32 / (self.ends_at - self.started_at) as f32
+    }
+}
+
+/// Text drawn over the action instead of a mesh, like the points for a kill. It fades out
+/// as its `Lifetime` runs down.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct FloatingText {
+    pub text: String,
+    pub color: Color,
+}
+
+/// The power-ups birds can drop. Most are an effect that runs on the player for a while
+/// after it has been picked up. `ClearScreen` is kept as a bomb until used instead, and
+/// `ExtraBullet` grows the bullet pool for good.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerUp {
+    MachineGun,
+    ExtraBullet,
+    ClearScreen,
+    GrenadeLauncher,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 4] = [
+        PowerUp::MachineGun,
+        PowerUp::ExtraBullet,
+        PowerUp::ClearScreen,
+        PowerUp::GrenadeLauncher,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUp::MachineGun => "Machine gun",
+            PowerUp::ExtraBullet => "Extra bullet",
+            PowerUp::ClearScreen => "Clear screen",
+            PowerUp::GrenadeLauncher => "Grenade launcher",
+        }
+    }
+}
+
+/// Something the player collects by touching it. It disappears if left lying around too
+/// long.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct Pickup {
+    pub power_up: PowerUp,
+    pub expires_at: u128,
+}
+
+/// The power-ups working on the player right now and when each one wears off, in
+/// milliseconds since the game started.
+#[derive(Component, Debug, Default)]
+#[storage(DenseVecStorage)]
+pub struct ActiveEffects(HashMap<PowerUp, u128>);
+
+impl ActiveEffects {
+    pub fn new() -> ActiveEffects {
+        ActiveEffects(HashMap::new())
+    }
+
+    /// Starts the effect, or extends it if it is already running.
+    pub fn start(&mut self, power_up: PowerUp, until: u128) {
+        let ends_at = self.0.entry(power_up).or_insert(until);
+        if *ends_at < until {
+            *ends_at = until;
+        }
+    }
+
+    pub fn is_active(&self, power_up: PowerUp) -> bool {
+        self.0.contains_key(&power_up)
+    }
+
+    /// Removes every effect that has run out.
+    pub fn expire(&mut self, now: u128) {
+        self.0.retain(|_power_up, ends_at| *ends_at > now);
+    }
+
+    /// Running effects in a stable order, for display.
+    pub fn iter(&self) -> impl Iterator<Item = (PowerUp, u128)> + '_ {
+        PowerUp::ALL
+            .iter()
+            .filter_map(move |power_up| self.0.get(power_up).map(|ends_at| (*power_up, *ends_at)))
+    }
+}
+
+/// Marks a bullet that only exists while the machine gun is running. It is removed once
+/// the effect has worn off and the bullet is back in the pool.
+#[derive(Component, Debug, Default)]
+#[storage(NullStorage)]
+pub struct TemporaryBullet;
+
+#[derive(Component, Debug, Default)]
+#[storage(NullStorage)]
+pub struct Grenade;
+
+/// A blast that grows from nothing to `max_radius` over its lifetime. Every bird caught
+/// inside it dies, and `kills` counts them so later kills in the same blast score more.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct Explosion {
+    pub radius: f32,
+    pub max_radius: f32,
+    pub started_at: u128,
+    pub ends_at: u128,
+    pub kills: usize,
+}
+
+impl Explosion {
+    pub fn new(max_radius: f32, started_at: u128, ends_at: u128) -> Explosion {
+        Explosion {
+            radius: 0.0,
+            max_radius,
+            started_at,
+            ends_at,
+            kills: 0,
+        }
+    }
+}
+
+/// Screen-clearing bombs the player is holding on to.
+#[derive(Component, Debug, Default)]
+#[storage(DenseVecStorage)]
+pub struct Bombs(usize);
+
+impl Bombs {
+    pub fn new() -> Bombs {
+        Bombs(0)
+    }
+
+    pub fn get(&self) -> usize {
+        self.0
+    }
+
+    /// Picks up another bomb unless already carrying `max`.
+    pub fn add(&mut self, max: usize) {
+        if self.0 < max {
+            self.0 += 1;
+        }
+    }
+
+    /// Uses up a bomb, returning false if there were none left.
+    pub fn take(&mut self) -> bool {
+        if self.0 == 0 {
+            return false;
+        }
+        self.0 -= 1;
+        true
+    }
+}
+
+/// The shapes a bird's wings can be drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WingPose {
+    Up,
+    Level,
+    Down,
+    Tucked,
+}
+
+impl WingPose {
+    pub const ALL: [WingPose; 4] = [
+        WingPose::Up,
+        WingPose::Level,
+        WingPose::Down,
+        WingPose::Tucked,
+    ];
+}
+
+/// Cycles an entity's mesh through `frames`. Progress is counted in frames, so changing
+/// the speed never makes the animation jump. While `hold` is set the animation keeps
+/// ticking but shows that frame instead.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct Animation {
+    frames: Vec<MeshType>,
+    frames_per_second: f32,
+    progress: f32,
+    hold: Option<MeshType>,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<MeshType>, frames_per_second: f32) -> Animation {
+        Animation {
+            frames,
+            frames_per_second,
+            progress: 0.0,
+            hold: None,
+        }
+    }
+
+    pub fn set_speed(&mut self, frames_per_second: f32) {
+        self.frames_per_second = frames_per_second;
+    }
+
+    pub fn hold(&mut self, frame: Option<MeshType>) {
+        self.hold = frame;
+    }
+
+    pub fn advance(&mut self, delta_time: f32) {
+        self.progress =
+            (self.progress + delta_time * self.frames_per_second) % self.frames.len() as f32;
+    }
+
+    pub fn current(&self) -> MeshType {
+        self.hold
+            .unwrap_or_else(|| self.frames[self.progress as usize % self.frames.len()])
+    }
+}
+
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct BirdSpecies(Species);
+
+impl BirdSpecies {
+    pub fn new(species: Species) -> BirdSpecies {
+        BirdSpecies(species)
+    }
+
+    pub fn get(&self) -> Species {
+        self.0
+    }
+}
+
+/// How many more bullets it takes to bring a bird down.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct HitPoints(u32);
+
+impl HitPoints {
+    pub fn new(hit_points: u32) -> HitPoints {
+        HitPoints(hit_points)
+    }
+
+    /// Takes one hit point away and returns true if that was the last one.
+    pub fn hit(&mut self) -> bool {
+        self.0 = self.0.saturating_sub(1);
+        self.0 == 0
+    }
+}
+
+/// The outline an entity collides with, centred on its position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Circle {
+        radius: f32,
+    },
+    /// An axis-aligned box reaching `half_width` either side and `half_height` above and
+    /// below.
+    Box {
+        half_width: f32,
+        half_height: f32,
+    },
+}
+
+impl Shape {
+    /// How far the shape reaches from its centre along each axis.
+    pub fn half_extents(&self) -> (f32, f32) {
+        match *self {
+            Shape::Circle { radius } => (radius, radius),
+            Shape::Box {
+                half_width,
+                half_height,
+            } => (half_width, half_height),
+        }
+    }
+
+    /// Whether this shape at `position` overlaps `other` at `other_position`. Shapes that
+    /// only touch along an edge don't count.
+    pub fn overlaps(&self, position: &Position, other: &Shape, other_position: &Position) -> bool {
+        let x = other_position.x - position.x;
+        let y = other_position.y - position.y;
+
+        match (*self, *other) {
+            (
+                Shape::Circle { radius },
+                Shape::Circle {
+                    radius: other_radius,
+                },
+            ) => (x * x + y * y).sqrt() < radius + other_radius,
+            (
+                Shape::Box {
+                    half_width,
+                    half_height,
+                },
+                Shape::Box {
+                    half_width: other_half_width,
+                    half_height: other_half_height,
+                },
+            ) => {
+                x.abs() < half_width + other_half_width && y.abs() < half_height + other_half_height
+            }
+            (
+                Shape::Circle { radius },
+                Shape::Box {
+                    half_width,
+                    half_height,
+                },
+            ) => {
+                // the point on the box closest to the middle of the circle
+                let nearest_x = x.max(-half_width).min(half_width) - x;
+                let nearest_y = y.max(-half_height).min(half_height) - y;
+                (nearest_x * nearest_x + nearest_y * nearest_y).sqrt() < radius
+            }
+            (Shape::Box { .. }, Shape::Circle { .. }) => {
+                other.overlaps(other_position, self, position)
+            }
+        }
+    }
+}
+
+/// A set of collision layers. Every collider is on one layer and has a mask of the layers
+/// it wants to hear about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layers(u32);
+
+impl Layers {
+    pub const NONE: Layers = Layers(0);
+    pub const PLAYER: Layers = Layers(1);
+    pub const EGG: Layers = Layers(1 << 1);
+    pub const BIRD: Layers = Layers(1 << 2);
+    pub const BULLET: Layers = Layers(1 << 3);
+    pub const GRENADE: Layers = Layers(1 << 4);
+    pub const EXPLOSION: Layers = Layers(1 << 5);
+    pub const POOP: Layers = Layers(1 << 6);
+    pub const PICKUP: Layers = Layers(1 << 7);
+
+    pub fn intersects(&self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, other: Layers) -> Layers {
+        Layers(self.0 | other.0)
+    }
+}
+
+/// Lets `DetectCollisionsSystem` know the entity can touch things. A collision is only
+/// reported for the collider whose `mask` includes the other collider's `layer`.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct Collider {
+    pub shape: Shape,
+    pub layer: Layers,
+    pub mask: Layers,
+}
+
+impl Collider {
+    pub fn new(shape: Shape, layer: Layers, mask: Layers) -> Collider {
+        Collider { shape, layer, mask }
+    }
+
+    pub fn circle(radius: f32, layer: Layers, mask: Layers) -> Collider {
+        Collider::new(Shape::Circle { radius }, layer, mask)
+    }
+
+    pub fn rectangle(half_width: f32, half_height: f32, layer: Layers, mask: Layers) -> Collider {
+        Collider::new(
+            Shape::Box {
+                half_width,
+                half_height,
+            },
+            layer,
+            mask,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32, y: f32) -> Position {
+        Position { x, y }
+    }
+
+    const CIRCLE: Shape = Shape::Circle { radius: 5.0 };
+    const BOX: Shape = Shape::Box {
+        half_width: 10.0,
+        half_height: 2.0,
+    };
+
+    #[test]
+    fn circles_overlap_closer_than_their_radii() {
+        assert!(CIRCLE.overlaps(&at(0.0, 0.0), &CIRCLE, &at(6.0, 6.0)));
+        assert!(!CIRCLE.overlaps(&at(0.0, 0.0), &CIRCLE, &at(8.0, 8.0)));
+        assert!(!CIRCLE.overlaps(&at(0.0, 0.0), &CIRCLE, &at(10.0, 0.0)));
+    }
+
+    #[test]
+    fn boxes_overlap_on_both_axes() {
+        assert!(BOX.overlaps(&at(0.0, 0.0), &BOX, &at(19.0, 3.0)));
+        assert!(!BOX.overlaps(&at(0.0, 0.0), &BOX, &at(19.0, 4.0)));
+        assert!(!BOX.overlaps(&at(0.0, 0.0), &BOX, &at(20.0, 0.0)));
+    }
+
+    #[test]
+    fn circles_and_boxes_overlap_either_way_round() {
+        // past the corner of the box, inside its bounding square but not the circle
+        let corner = at(14.0, 6.0);
+        assert!(!CIRCLE.overlaps(&corner, &BOX, &at(0.0, 0.0)));
+        assert!(!BOX.overlaps(&at(0.0, 0.0), &CIRCLE, &corner));
+
+        let side = at(0.0, 6.0);
+        assert!(CIRCLE.overlaps(&side, &BOX, &at(0.0, 0.0)));
+        assert!(BOX.overlaps(&at(0.0, 0.0), &CIRCLE, &side));
+
+        // centre of the circle inside the box
+        assert!(CIRCLE.overlaps(&at(3.0, 1.0), &BOX, &at(0.0, 0.0)));
+    }
+
+    #[test]
+    fn effects_wear_off_in_the_order_they_end() {
+        let mut active_effects = ActiveEffects::new();
+        active_effects.start(PowerUp::GrenadeLauncher, 200);
+        active_effects.start(PowerUp::MachineGun, 100);
+
+        active_effects.expire(99);
+        assert!(active_effects.is_active(PowerUp::MachineGun));
+        assert!(active_effects.is_active(PowerUp::GrenadeLauncher));
+
+        active_effects.expire(100);
+        assert!(!active_effects.is_active(PowerUp::MachineGun));
+        assert!(active_effects.is_active(PowerUp::GrenadeLauncher));
+
+        active_effects.expire(200);
+        assert_eq!(active_effects.iter().count(), 0);
+    }
+
+    #[test]
+    fn picking_up_a_running_effect_only_ever_extends_it() {
+        let mut active_effects = ActiveEffects::new();
+        active_effects.start(PowerUp::MachineGun, 300);
+        active_effects.start(PowerUp::MachineGun, 200);
+        active_effects.start(PowerUp::GrenadeLauncher, 100);
+
+        let running: Vec<(PowerUp, u128)> = active_effects.iter().collect();
+        assert_eq!(
+            running,
+            vec![(PowerUp::MachineGun, 300), (PowerUp::GrenadeLauncher, 100)]
+        );
+    }
+}
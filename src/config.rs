@@ -0,0 +1,447 @@
+use super::species::{Species, SpeciesStats};
+use super::waves::WaveSpec;
+use ggez::{GameError, GameResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Gameplay tuning that isn't fixed by the rules of the game. Stored in the world as a
+/// resource so systems can read whatever they need.
+///
+/// Loaded from a TOML file so the game can be balanced without recompiling. Any setting
+/// left out of the file keeps the default from `Config::new`, and `config.toml` in the
+/// repository lists every one of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Downward pull on anything that falls, in pixels per second squared.
+    pub gravity: f32,
+    /// How hard the player pushes off when running, in pixels per second squared.
+    pub player_acceleration: f32,
+    /// How quickly the player slows down, as a fraction of their speed per second.
+    pub player_drag: f32,
+    /// Upward speed at the start of a jump, in pixels per second.
+    pub jump_speed: f32,
+    /// In pixels per second.
+    pub bullet_speed: f32,
+    /// Shortest time between two shots, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub fire_cooldown: u128,
+    /// How close a bullet has to get to a normal sized bird to hit it, in pixels.
+    pub bird_hit_distance: f32,
+    /// Size of a normal sized bird, in pixels. Each species scales this.
+    pub bird_width: f32,
+    pub bird_height: f32,
+    /// Whether a bird that flies off the left or right edge comes back in on the other
+    /// side. Only applies to birds that arrive after it is changed.
+    pub bird_wrap_around: bool,
+    /// Birds in the first wave the director makes up.
+    pub first_wave_size: usize,
+    /// Each made up wave has this many more birds than the one before, up to
+    /// `max_birds_per_wave`.
+    pub wave_size_growth: usize,
+    pub max_birds_per_wave: usize,
+    /// Time between one bird of a made up wave and the next, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub wave_spawn_interval: u128,
+    /// Calm between clearing a made up wave and the next one starting, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub wave_pause: u128,
+    /// First made up wave where birds also come in from the left and right.
+    pub side_spawns_from_wave: usize,
+    /// Points for clearing a wave, multiplied by the wave number.
+    pub wave_clear_bonus: usize,
+    /// How often the player scores a point just for keeping the egg, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub survival_score_interval: u128,
+    /// Kills closer together than this keep a combo going, in milliseconds. Each time a
+    /// window passes without a kill the multiplier drops back a level.
+    #[serde(with = "milliseconds")]
+    pub combo_window: u128,
+    /// Kills in a row needed to raise the combo multiplier by one.
+    pub kills_per_combo_level: usize,
+    /// The highest the combo multiplier goes.
+    pub max_combo_multiplier: usize,
+    /// Extra points for a kill made while the player is off the ground.
+    pub mid_air_bonus: usize,
+    /// Extra points for shooting down a bird that is carrying the egg.
+    pub egg_carrier_bonus: usize,
+    /// How long text that pops up over the action takes to fade out, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub popup_duration: u128,
+    /// How fast popup text floats upwards, in pixels per second.
+    pub popup_rise_speed: f32,
+    /// How long a hit from bird poop stops the player from moving or shooting, in
+    /// milliseconds.
+    #[serde(with = "milliseconds")]
+    pub stun_duration: u128,
+    /// Shortest time a bird waits between droppings, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub poop_interval_min: u128,
+    /// Longest time a bird waits between droppings, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub poop_interval_max: u128,
+    /// Chance from 0 to 1 that a bird drops a power-up when it is shot.
+    pub power_up_drop_chance: f64,
+    /// How long a power-up lasts once collected, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub power_up_duration: u128,
+    /// How long a dropped power-up waits to be collected before vanishing, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub pickup_lifetime: u128,
+    /// Bullets the player starts each game with.
+    pub starting_bullets: usize,
+    /// Most bullets the extra bullet power-up can grow the pool to.
+    pub max_bullets: usize,
+    /// Bullets added to the pool while the machine gun is running.
+    pub machine_gun_extra_bullets: usize,
+    /// Speed a grenade leaves the launcher at, in pixels per second.
+    pub grenade_speed: f32,
+    /// Shortest time between two grenades, in milliseconds.
+    #[serde(with = "milliseconds")]
+    pub grenade_cooldown: u128,
+    /// How far a grenade blast reaches at its biggest, in pixels.
+    pub grenade_blast_radius: f32,
+    /// Most screen-clearing bombs the player can carry at once.
+    pub max_bombs: usize,
+    /// Hand-written waves played before the director starts making them up.
+    pub waves: Vec<WaveSpec>,
+    pub pigeon: SpeciesStats,
+    pub hawk: SpeciesStats,
+    pub goose: SpeciesStats,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            gravity: 600.0,
+            player_acceleration: 400.0,
+            player_drag: 2.0,
+            jump_speed: 300.0,
+            bullet_speed: 150.0,
+            fire_cooldown: 100,
+            bird_hit_distance: 25.0,
+            bird_width: 25.0,
+            bird_height: 10.0,
+            bird_wrap_around: false,
+            first_wave_size: 3,
+            wave_size_growth: 2,
+            max_birds_per_wave: 50,
+            wave_spawn_interval: 400,
+            wave_pause: 4000,
+            side_spawns_from_wave: 4,
+            wave_clear_bonus: 25,
+            survival_score_interval: 5000,
+            combo_window: 3000,
+            kills_per_combo_level: 3,
+            max_combo_multiplier: 5,
+            mid_air_bonus: 5,
+            egg_carrier_bonus: 20,
+            popup_duration: 1000,
+            popup_rise_speed: 60.0,
+            stun_duration: 1500,
+            poop_interval_min: 4000,
+            poop_interval_max: 10000,
+            power_up_drop_chance: 0.1,
+            power_up_duration: 8000,
+            pickup_lifetime: 10000,
+            starting_bullets: 3,
+            max_bullets: 8,
+            machine_gun_extra_bullets: 12,
+            grenade_speed: 700.0,
+            grenade_cooldown: 600,
+            grenade_blast_radius: 80.0,
+            max_bombs: 3,
+            waves: vec![],
+            pigeon: SpeciesStats {
+                color: [1.0, 1.0, 1.0],
+                size: 1.0,
+                acceleration: 3.0,
+                max_speed: 80.0,
+                hit_points: 1,
+                score: 10,
+                spawn_weight: 10,
+                appears_after: 0,
+            },
+            hawk: SpeciesStats {
+                color: [1.0, 0.2, 0.2],
+                size: 0.8,
+                acceleration: 8.0,
+                max_speed: 150.0,
+                hit_points: 1,
+                score: 20,
+                spawn_weight: 4,
+                appears_after: 30000,
+            },
+            goose: SpeciesStats {
+                color: [0.6, 0.6, 0.6],
+                size: 1.4,
+                acceleration: 1.5,
+                max_speed: 35.0,
+                hit_points: 3,
+                score: 30,
+                spawn_weight: 3,
+                appears_after: 60000,
+            },
+        }
+    }
+
+    /// Reads a config file and checks every value in it makes sense.
+    pub fn load(path: &Path) -> GameResult<Config> {
+        let text = fs::read_to_string(path)?;
+        let config: Config = toml::from_str(&text).map_err(|error| invalid(&error.to_string()))?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Describes every setting that differs between `self` and `other`, one per line, as
+    /// `name: old -> new`. Species settings are named like `hawk.max_speed`.
+    pub fn changes(&self, other: &Config) -> Vec<String> {
+        let mut changes = vec![];
+        if let (Ok(before), Ok(after)) = (toml::Value::try_from(self), toml::Value::try_from(other))
+        {
+            list_changes("", &before, &after, &mut changes);
+        }
+        changes
+    }
+
+    pub fn species(&self, species: Species) -> &SpeciesStats {
+        match species {
+            Species::Pigeon => &self.pigeon,
+            Species::Hawk => &self.hawk,
+            Species::Goose => &self.goose,
+        }
+    }
+
+    pub fn validate(&self) -> GameResult<()> {
+        let positive = [
+            ("gravity", self.gravity),
+            ("player_acceleration", self.player_acceleration),
+            ("jump_speed", self.jump_speed),
+            ("bullet_speed", self.bullet_speed),
+            ("bird_hit_distance", self.bird_hit_distance),
+            ("bird_width", self.bird_width),
+            ("bird_height", self.bird_height),
+            ("grenade_speed", self.grenade_speed),
+            ("grenade_blast_radius", self.grenade_blast_radius),
+        ];
+        for (name, value) in positive.iter() {
+            if !value.is_finite() || *value <= 0.0 {
+                return Err(invalid(&format!(
+                    "{} must be above 0, found {}",
+                    name, value
+                )));
+            }
+        }
+        if !self.player_drag.is_finite() || self.player_drag < 0.0 {
+            return Err(invalid(&format!(
+                "player_drag can't be negative, found {}",
+                self.player_drag
+            )));
+        }
+        if !self.popup_rise_speed.is_finite() {
+            return Err(invalid(&format!(
+                "popup_rise_speed must be a number, found {}",
+                self.popup_rise_speed
+            )));
+        }
+        if self.survival_score_interval == 0 {
+            return Err(invalid("survival_score_interval must be above 0"));
+        }
+        if self.combo_window == 0 {
+            return Err(invalid("combo_window must be above 0"));
+        }
+        if self.kills_per_combo_level == 0 || self.max_combo_multiplier == 0 {
+            return Err(invalid(
+                "kills_per_combo_level and max_combo_multiplier must be at least 1",
+            ));
+        }
+        if self.first_wave_size == 0 || self.max_birds_per_wave == 0 {
+            return Err(invalid(
+                "first_wave_size and max_birds_per_wave must be at least 1",
+            ));
+        }
+        if self.side_spawns_from_wave == 0 {
+            return Err(invalid("side_spawns_from_wave must be at least 1"));
+        }
+        for (index, wave) in self.waves.iter().enumerate() {
+            if Species::ALL.iter().all(|species| wave.count(*species) == 0) {
+                return Err(invalid(&format!("wave {} has no birds", index + 1)));
+            }
+            if wave.edges.is_empty() {
+                return Err(invalid(&format!(
+                    "wave {} needs at least one spawn edge",
+                    index + 1
+                )));
+            }
+        }
+        if self.poop_interval_min > self.poop_interval_max {
+            return Err(invalid(
+                "poop_interval_min can't be longer than poop_interval_max",
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.power_up_drop_chance) {
+            return Err(invalid("power_up_drop_chance must be between 0 and 1"));
+        }
+        if self.starting_bullets == 0 || self.starting_bullets > self.max_bullets {
+            return Err(invalid(
+                "starting_bullets must be at least 1 and no more than max_bullets",
+            ));
+        }
+
+        for species in Species::ALL.iter() {
+            let stats = self.species(*species);
+            let name = species.name();
+            if [stats.size, stats.acceleration, stats.max_speed]
+                .iter()
+                .any(|value| !value.is_finite() || *value <= 0.0)
+            {
+                return Err(invalid(&format!(
+                    "{} size, acceleration and max_speed must be above 0",
+                    name
+                )));
+            }
+            if stats.hit_points == 0 {
+                return Err(invalid(&format!("{} needs at least 1 hit point", name)));
+            }
+            if stats
+                .color
+                .iter()
+                .any(|channel| !(0.0..=1.0).contains(channel))
+            {
+                return Err(invalid(&format!(
+                    "{} color channels must be between 0 and 1",
+                    name
+                )));
+            }
+        }
+        // something has to be able to spawn from the very first flock
+        if !Species::ALL.iter().any(|species| {
+            let stats = self.species(*species);
+            stats.appears_after == 0 && stats.spawn_weight > 0
+        }) {
+            return Err(invalid(
+                "at least one species needs a spawn_weight above 0 and appears_after 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+/// Notices when the config file is saved so a running game can pick up the new values.
+/// Polls the modified time rather than asking the OS for events, which is cheap enough to
+/// do every frame.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> ConfigWatcher {
+        let modified = modified_time(&path);
+        ConfigWatcher { path, modified }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the file again if it has been saved since the last poll. A file that is
+    /// missing for a moment, as some editors do while saving, is just checked again later.
+    pub fn poll(&mut self) -> Option<GameResult<Config>> {
+        let modified = modified_time(&self.path)?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+
+        self.modified = Some(modified);
+        Some(Config::load(&self.path))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn list_changes(name: &str, before: &toml::Value, after: &toml::Value, changes: &mut Vec<String>) {
+    match (before, after) {
+        (toml::Value::Table(before), toml::Value::Table(after)) => {
+            for (key, value) in before {
+                let name = if name.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", name, key)
+                };
+                if let Some(new_value) = after.get(key) {
+                    list_changes(&name, value, new_value, changes);
+                }
+            }
+        }
+        _ if before != after => changes.push(format!("{}: {} -> {}", name, before, after)),
+        _ => (),
+    }
+}
+
+fn invalid(reason: &str) -> GameError {
+    GameError::ResourceLoadError(format!("invalid config: {}", reason))
+}
+
+/// TOML only has 64 bit integers, so times are written as `u64` and widened to the `u128`
+/// the rest of the game measures time in.
+pub(crate) mod milliseconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::convert::TryFrom;
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = u64::try_from(*value).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_u64(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        u64::deserialize(deserializer).map(u128::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(Config::new().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_numbers_that_are_not_finite() {
+        let mut config = Config::new();
+        config.player_drag = f32::NAN;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::new();
+        config.popup_rise_speed = f32::INFINITY;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::new();
+        config.gravity = f32::INFINITY;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn nan_in_the_file_is_rejected() {
+        let text = toml::to_string(&Config::new())
+            .unwrap()
+            .replace("player_drag = ", "player_drag = nan # ");
+        let config: Config = toml::from_str(&text).unwrap();
+        assert!(config.validate().is_err());
+    }
+}
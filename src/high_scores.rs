@@ -0,0 +1,332 @@
+use ggez::{filesystem, Context, GameError, GameResult};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HEADER: &str = "keep_it_alive high scores 1";
+const FILE_NAME: &str = "high_scores.txt";
+pub const MAX_ENTRIES: usize = 10;
+pub const MAX_NAME_LENGTH: usize = 12;
+
+/// One run on the table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: usize,
+    /// Milliseconds the egg survived.
+    pub survived: u128,
+    /// The day the run was played, as year-month-day.
+    pub date: String,
+    pub seed: u64,
+}
+
+impl HighScore {
+    /// A run played today. Tabs and line breaks are taken out of the name so it can't
+    /// break the file.
+    pub fn new(name: &str, score: usize, survived: u128, seed: u64) -> HighScore {
+        let name: String = name
+            .chars()
+            .filter(|character| !character.is_control())
+            .take(MAX_NAME_LENGTH)
+            .collect();
+
+        HighScore {
+            name: name.trim().to_string(),
+            score,
+            survived,
+            date: today(),
+            seed,
+        }
+    }
+}
+
+/// The best runs on this machine, best first, kept in the user's data directory between
+/// games.
+///
+/// Saved as plain text with one run per line, its fields separated by tabs and the name
+/// last.
+#[derive(Debug, Clone, Default)]
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    pub fn new() -> HighScores {
+        HighScores { entries: vec![] }
+    }
+
+    /// Where the table lives for this game.
+    pub fn path(context: &Context) -> PathBuf {
+        filesystem::user_data_dir(context).join(FILE_NAME)
+    }
+
+    /// A missing file is just an empty table, since nobody has played yet.
+    pub fn load(path: &Path) -> GameResult<HighScores> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(HighScores::new()),
+            Err(error) if error.kind() == ErrorKind::InvalidData => {
+                return Err(invalid("not a text file"))
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("not a high score file"));
+        }
+        let mut high_scores = HighScores::new();
+        for line in lines.filter(|line| !line.is_empty()) {
+            high_scores.insert(parse_entry(line)?);
+        }
+        Ok(high_scores)
+    }
+
+    /// Like `load`, but starts an empty table when the file is broken instead of failing.
+    /// A broken file is moved aside rather than overwritten by the next save, in case it
+    /// can be fixed by hand. Only fails if the file couldn't be read at all, which may not
+    /// last, so it is left where it is.
+    pub fn load_or_new(path: &Path) -> GameResult<HighScores> {
+        match HighScores::load(path) {
+            Ok(high_scores) => Ok(high_scores),
+            Err(error @ GameError::IOError(_)) => Err(error),
+            Err(error) => {
+                println!(
+                    "Starting a new high score table, {} has an error: {}",
+                    path.display(),
+                    error
+                );
+                let backup = path.with_extension("txt.bad");
+                match fs::rename(path, &backup) {
+                    Ok(()) => println!("The old table was moved to {}", backup.display()),
+                    Err(error) => println!("Couldn't move the old table aside: {}", error),
+                }
+                Ok(HighScores::new())
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> GameResult<()> {
+        let mut text = format!("{}\n", HEADER);
+        for entry in &self.entries {
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                entry.score, entry.survived, entry.date, entry.seed, entry.name
+            ));
+        }
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[HighScore] {
+        &self.entries
+    }
+
+    /// Whether a run with this score would make it onto the table.
+    pub fn qualifies(&self, score: usize) -> bool {
+        if score == 0 {
+            return false;
+        }
+        self.entries.len() < MAX_ENTRIES
+            || self
+                .entries
+                .last()
+                .is_some_and(|lowest| score > lowest.score)
+    }
+
+    /// Adds the run below any with the same score, since they got there first, and drops
+    /// whatever falls off the bottom. Returns where it ended up, if it stayed on.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let position = self
+            .entries
+            .iter()
+            .position(|existing| existing.score < entry.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(position, entry);
+        self.entries.truncate(MAX_ENTRIES);
+
+        if position < self.entries.len() {
+            Some(position)
+        } else {
+            None
+        }
+    }
+}
+
+fn invalid(message: &str) -> GameError {
+    GameError::ResourceLoadError(format!("Invalid high score file: {}", message))
+}
+
+fn parse_entry(line: &str) -> GameResult<HighScore> {
+    let mut fields = line.splitn(5, '\t');
+    let mut next_field = || fields.next().ok_or_else(|| invalid("missing field"));
+    let score = next_field()?.parse().map_err(|_| invalid("bad score"))?;
+    let survived = next_field()?
+        .parse()
+        .map_err(|_| invalid("bad survival time"))?;
+    let date = next_field()?.to_string();
+    let seed = next_field()?.parse().map_err(|_| invalid("bad seed"))?;
+    let name = next_field()?.to_string();
+
+    Ok(HighScore {
+        name,
+        score,
+        survived,
+        date,
+        seed,
+    })
+}
+
+/// Today's date in UTC, worked out from the system clock so there's no need for a date
+/// library.
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let days = (seconds / 86_400) as i64;
+
+    // count from 1 March 0000 so leap days fall at the end of each year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("keep_it_alive_{}_{}", std::process::id(), name))
+    }
+
+    fn entry(name: &str, score: usize) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            score,
+            survived: 1000,
+            date: "2020-02-29".to_string(),
+            seed: 1,
+        }
+    }
+
+    #[test]
+    fn inserts_in_order_and_after_ties() {
+        let mut high_scores = HighScores::new();
+        assert_eq!(high_scores.insert(entry("first", 50)), Some(0));
+        assert_eq!(high_scores.insert(entry("second", 100)), Some(0));
+        assert_eq!(high_scores.insert(entry("tied", 50)), Some(2));
+
+        let names: Vec<&str> = high_scores
+            .entries()
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["second", "first", "tied"]);
+    }
+
+    #[test]
+    fn keeps_only_the_best() {
+        let mut high_scores = HighScores::new();
+        for score in 1..=MAX_ENTRIES {
+            high_scores.insert(entry("player", score * 10));
+        }
+        assert!(!high_scores.qualifies(10));
+        assert!(high_scores.qualifies(11));
+        assert_eq!(high_scores.insert(entry("late", 10)), None);
+        assert_eq!(
+            high_scores.insert(entry("better", 11)),
+            Some(MAX_ENTRIES - 1)
+        );
+        assert_eq!(high_scores.entries().len(), MAX_ENTRIES);
+        assert!(!HighScores::new().qualifies(0));
+    }
+
+    #[test]
+    fn names_lose_control_characters_and_length() {
+        let high_score = HighScore::new(" tab\there\nand a very long name", 1, 0, 0);
+        // trimmed after cutting it down, so the spaces count towards the length
+        assert_eq!(high_score.name, "tabhereand");
+    }
+
+    #[test]
+    fn round_trip_keeps_every_entry() {
+        let path = temp_path("round_trip_high_scores.txt");
+        let mut high_scores = HighScores::new();
+        high_scores.insert(entry("with\ttab", 100));
+        high_scores.insert(entry("second", 20));
+        high_scores.save(&path).unwrap();
+
+        let loaded = HighScores::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.entries(), high_scores.entries());
+    }
+
+    #[test]
+    fn a_missing_file_is_an_empty_table() {
+        let loaded = HighScores::load(&temp_path("missing_high_scores.txt")).unwrap();
+        assert!(loaded.entries().is_empty());
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        let path = temp_path("broken_high_scores.txt");
+        fs::write(
+            &path,
+            format!("{}\n100\tlong\t2020-02-29\t1\tname\n", HEADER),
+        )
+        .unwrap();
+        let result = HighScores::load(&path);
+        fs::write(&path, "not a table\n").unwrap();
+        let no_header = HighScores::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+        assert!(no_header.is_err());
+    }
+
+    #[test]
+    fn only_broken_files_are_moved_aside() {
+        let path = temp_path("moved_high_scores.txt");
+        fs::write(&path, "not a table\n").unwrap();
+        let high_scores = HighScores::load_or_new(&path).unwrap();
+        let backup = path.with_extension("txt.bad");
+        let moved = !path.exists() && backup.exists();
+        fs::remove_file(&backup).unwrap();
+        assert!(high_scores.entries().is_empty());
+        assert!(moved);
+
+        fs::write(&path, [0xff, 0xfe, 0x00]).unwrap();
+        HighScores::load_or_new(&path).unwrap();
+        let moved = !path.exists() && backup.exists();
+        fs::remove_file(&backup).unwrap();
+        assert!(moved);
+
+        // a directory in the way can't be read, but there's nothing wrong with what's there
+        let path = temp_path("unreadable_high_scores.txt");
+        fs::create_dir_all(&path).unwrap();
+        let result = HighScores::load_or_new(&path);
+        let left_alone = path.is_dir();
+        fs::remove_dir(&path).unwrap();
+        assert!(result.is_err());
+        assert!(left_alone);
+    }
+}
@@ -1,242 +1,371 @@
-mod components;
-mod meshes;
-mod resources;
-mod systems;
-
-use components::{
-    Acceleration, Bullet, BulletState, Drag, Floor, Flyer, HasGravity, Height, KeepAlive,
-    ObjectMesh, OnGround, Player, Position, Radius, Velocity, Width,
-};
-use ggez::event::{EventHandler, KeyCode};
-use ggez::graphics::{DrawMode, DrawParam, Mesh, MeshBuilder};
+pub mod components;
+pub mod config;
+pub mod high_scores;
+pub mod meshes;
+pub mod replay;
+pub mod resources;
+pub mod scoring;
+pub mod screens;
+pub mod simulation;
+pub mod spatial_grid;
+pub mod species;
+pub mod systems;
+pub mod waves;
+
+pub use config::{Config, ConfigWatcher};
+use ggez::event::{self, EventHandler, KeyCode, KeyMods};
 use ggez::input::{keyboard, mouse};
 use ggez::nalgebra::Point2;
 use ggez::{graphics, timer, Context, GameResult};
-use rand::prelude::*;
-use resources::{BulletSize, DelayFiringUntilAfter, Score, StillAlive};
+use high_scores::{HighScore, HighScores, MAX_NAME_LENGTH};
+use meshes::Meshes;
+pub use replay::Replay;
+use resources::ArenaBounds;
+pub use simulation::{Input, Simulation, TICKS_PER_SECOND};
 use specs::prelude::*;
-use specs::ReadStorage;
-use systems::{
-    ApplyForceSystem, CheckEggSystem, DragSystem, FireBulletSystem, FlySystem, GravitySystem,
-    HideHitBullets, HitGround, IncreaseScoreBySurvivingSystem, LandOnEggSystem, MovePlayerSystem,
-    RenderSystem, ResetBulletsSystem, ShootBirdsSystem,
-};
+use std::path::PathBuf;
+use systems::RenderSystem;
+
+/// Where the input for each tick comes from.
+enum InputSource {
+    Live,
+    Recording(Replay, PathBuf),
+    Playback { replay: Replay, next_tick: usize },
+}
+
+/// The screen the game is currently on. Only `Playing` advances the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamePhase {
+    Title,
+    Playing,
+    Paused,
+    /// The run beat a high score and the player is typing their name.
+    EnterName,
+    GameOver,
+}
 
 pub struct GameState {
-    world: World,
-    create_bird_every_miliseconds: u128,
-    time_to_create_bird: u128,
-    birds_to_create_at_the_same_time: usize,
-    bird_mesh: Mesh,
-    bird_width: f32,
-    bird_height: f32,
-    increase_score_every_miliseconds: u128,
-    next_score_increase_time: u128,
+    simulation: Simulation,
+    meshes: Meshes,
+    input_source: InputSource,
+    phase: GamePhase,
+    config_watcher: Option<ConfigWatcher>,
+    high_scores: HighScores,
+    /// Where to save new high scores. Replays don't set any, since they aren't new runs, and
+    /// neither does a game that couldn't read the table.
+    high_scores_path: Option<PathBuf>,
+    name: String,
 }
 
 impl GameState {
-    pub fn new(context: &mut Context) -> GameResult<GameState> {
-        let (arena_width, arena_height) = graphics::drawable_size(context);
-        let egg_width = 5.0;
-        let egg_height = 15.0;
-        let egg_mesh = meshes::createEggMesh(context, egg_width, egg_height)?;
-        let player_height = 50.0;
-        let player_width = 15.0;
-        let player = meshes::createPersonMesh(context, player_width, player_height)?;
-        let floor = meshes::createFloor(context, arena_width, 5.0)?;
-        let mut world = World::new();
-        let bullet_size = 5.0;
-        let bird_width = 25.0;
-        let bird_height = 10.0;
-        world.register::<Position>();
-        world.register::<ObjectMesh>();
-        world.register::<HasGravity>();
-        world.register::<Floor>();
-        world.register::<Height>();
-        world.register::<Velocity>();
-        world.register::<Acceleration>();
-        world.register::<Drag>();
-        world.register::<OnGround>();
-        world.register::<Width>();
-        world.register::<KeepAlive>();
-        world.register::<Flyer>();
-        world.register::<Player>();
-        world.register::<Bullet>();
-        world.register::<Radius>();
-        world.register::<BulletState>();
-
-        world.insert(StillAlive::new());
-        world.insert(BulletSize::new(bullet_size));
-        world.insert(DelayFiringUntilAfter::new());
-        world.insert(Score::new());
-
-        // egg
-        world
-            .create_entity()
-            .with(Position {
-                x: arena_width / 2.0,
-                y: arena_height - 25.0,
-            })
-            .with(ObjectMesh::new(egg_mesh))
-            .with(Width::new(egg_width))
-            .with(Height::new(egg_height))
-            .with(KeepAlive::new())
-            .build();
-
-        // player
-        world
-            .create_entity()
-            .with(Position {
-                x: 100.0,
-                y: arena_height - player_width - 500.0,
-            })
-            .with(ObjectMesh::new(player))
-            .with(HasGravity)
-            .with(Height::new(player_height / 2.0))
-            .with(Width::new(player_width))
-            .with(Velocity { x: 0.0, y: 0.0 })
-            .with(Acceleration { x: 0.0, y: 0.0 })
-            .with(Drag::new(0.0))
-            .with(OnGround::new())
-            .with(Player)
-            .build();
-
-        // floor
-        world
-            .create_entity()
-            .with(Position {
-                x: 0.0,
-                y: arena_height - 5.0,
-            })
-            .with(ObjectMesh::new(floor))
-            .with(Floor)
-            .build();
-
-        // bullets
-        for _ in 0..3 {
-            let bullet_mesh = meshes::createBullet(context, bullet_size)?;
-            world
-                .create_entity()
-                .with(Position { x: -50.0, y: -50.0 })
-                .with(ObjectMesh::new(bullet_mesh))
-                .with(Velocity { x: 0.0, y: 0.0 })
-                .with(Bullet)
-                .with(Radius::new(bullet_size))
-                .with(BulletState::new())
-                .build();
-        }
+    pub fn new(context: &mut Context, seed: u64, config: Config) -> GameResult<GameState> {
+        let arena = ArenaBounds::default();
+        let path = HighScores::path(context);
+        // without the old table a save would throw it away, so don't keep scores this time
+        let (high_scores, high_scores_path) = match HighScores::load_or_new(&path) {
+            Ok(high_scores) => (high_scores, Some(path)),
+            Err(error) => {
+                println!(
+                    "Couldn't read the high score table, scores won't be saved this game: {}",
+                    error
+                );
+                (HighScores::new(), None)
+            }
+        };
+
         Ok(GameState {
-            world,
-            create_bird_every_miliseconds: 3000,
-            time_to_create_bird: 0,
-            birds_to_create_at_the_same_time: 1,
-            bird_mesh: meshes::createBird(context, bird_width, bird_height)?,
-            bird_width,
-            bird_height,
-            increase_score_every_miliseconds: 5000,
-            next_score_increase_time: 5000,
+            meshes: Meshes::new(context, arena.width(), &config)?,
+            simulation: Simulation::new(arena, seed, config),
+            input_source: InputSource::Live,
+            phase: GamePhase::Title,
+            config_watcher: None,
+            high_scores,
+            high_scores_path,
+            name: String::new(),
         })
     }
-}
 
-impl GameState {
-    fn create_bird(&mut self, arena_width: f32, arena_height: f32) -> GameResult<()> {
-        for _ in 0..self.birds_to_create_at_the_same_time {
-            let mut rng = rand::thread_rng();
-            self.world
-                .create_entity()
-                .with(Position {
-                    x: rng.gen_range(-self.bird_width, arena_width + self.bird_width),
-                    y: -self.bird_height - 10.0,
-                })
-                .with(ObjectMesh::new(self.bird_mesh.clone()))
-                .with(Height::new(self.bird_height))
-                .with(Width::new(self.bird_width))
-                .with(Velocity { x: 0.0, y: 0.0 })
-                .with(Acceleration { x: 0.0, y: 0.0 })
-                .with(Drag::new(0.0))
-                .with(Flyer)
-                .build();
+    /// Plays live, keeping every tick's input so it can be written to `path` with
+    /// `save_recording` once the game closes. Restarting starts a new recording, so the
+    /// file always holds the most recent run.
+    pub fn recording(
+        context: &mut Context,
+        seed: u64,
+        path: PathBuf,
+        config: Config,
+    ) -> GameResult<GameState> {
+        let mut game_state = GameState::new(context, seed, config.clone())?;
+        let replay = Replay::new(seed, game_state.simulation.arena(), config);
+
+        game_state.input_source = InputSource::Recording(replay, path);
+        Ok(game_state)
+    }
+
+    /// Ignores the keyboard and mouse and feeds the simulation the recorded input instead,
+    /// using the config the run was recorded with rather than the one on disk.
+    pub fn playback(context: &mut Context, replay: Replay) -> GameResult<GameState> {
+        let arena = replay.arena();
+        let config = replay.config().clone();
+
+        Ok(GameState {
+            meshes: Meshes::new(context, arena.width(), &config)?,
+            simulation: Simulation::new(arena, replay.seed(), config),
+            input_source: InputSource::Playback {
+                replay,
+                next_tick: 0,
+            },
+            phase: GamePhase::Playing,
+            config_watcher: None,
+            // only shown, never saved, so a table that can't be read is left where it is
+            high_scores: HighScores::load(&HighScores::path(context)).unwrap_or_default(),
+            high_scores_path: None,
+            name: String::new(),
+        })
+    }
+
+    /// Applies changes to the config file while the game runs. Recordings and replays
+    /// don't watch, since a replay only stores the config it started with.
+    pub fn watch_config(&mut self, watcher: ConfigWatcher) {
+        self.config_watcher = Some(watcher);
+    }
+
+    pub fn phase(&self) -> GamePhase {
+        self.phase
+    }
+
+    pub fn save_recording(&self) -> GameResult<()> {
+        if let InputSource::Recording(replay, path) = &self.input_source {
+            replay.save(path)?;
+            println!("Saved {} ticks to {}", replay.ticks(), path.display());
         }
         Ok(())
     }
-}
 
-impl EventHandler for GameState {
-    fn update(&mut self, context: &mut Context) -> GameResult<()> {
-        let (arena_width, arena_height) = graphics::drawable_size(context);
-        let mut delta_time = ggez::timer::average_delta(context).as_secs_f32();
-        let pressed_keys = keyboard::pressed_keys(context);
-        let fps_cap = 1.0 / 60.0;
-        if delta_time < fps_cap {
-            delta_time = fps_cap;
-        }
-        let duration_since_start = timer::time_since_start(context).as_millis();
-        let mut gravity_system = GravitySystem { arena_height };
-        let mut move_system = ApplyForceSystem { delta_time };
-        let mut hit_ground = HitGround { arena_height };
-        let mut move_player_system = MovePlayerSystem { pressed_keys };
-        let mut drag_system = DragSystem;
-        let mut check_egg = CheckEggSystem;
-        let mut fly_system = FlySystem;
-        let mut landing_on_egg = LandOnEggSystem;
-        let mut reset_bullets = ResetBulletsSystem {
-            arena_width,
-            arena_height,
+    /// Throws the whole world away and builds a fresh one. Live games get a new seed while
+    /// a replay starts over from its first tick.
+    fn restart(&mut self) {
+        let arena = self.simulation.arena();
+        let seed = match &mut self.input_source {
+            InputSource::Live => rand::random(),
+            InputSource::Recording(replay, _path) => {
+                let seed = rand::random();
+                *replay = Replay::new(seed, arena, self.simulation.config());
+                seed
+            }
+            InputSource::Playback { replay, next_tick } => {
+                *next_tick = 0;
+                replay.seed()
+            }
+        };
+
+        println!("Seed: {}", seed);
+        self.simulation = Simulation::new(arena, seed, self.simulation.config());
+        self.phase = GamePhase::Playing;
+    }
+
+    /// Checks the watched config file and hands any valid changes to the simulation so they
+    /// apply from the next tick. An invalid edit is reported and the old values are kept.
+    fn reload_config(&mut self, context: &mut Context) -> GameResult<()> {
+        let watcher = match &mut self.config_watcher {
+            Some(watcher) => watcher,
+            None => return Ok(()),
         };
-        let mut shoot_bird_system = ShootBirdsSystem;
-        let mut hide_hit_bullets = HideHitBullets;
-        if mouse::button_pressed(context, mouse::MouseButton::Left) {
-            let mouse_location = mouse::position(context);
-            let mut fire_bullet_system = FireBulletSystem {
-                mouse_location: Point2::new(mouse_location.x, mouse_location.y),
-                duration_since_start: duration_since_start,
-            };
-            fire_bullet_system.run_now(&mut self.world);
+        let config = match watcher.poll() {
+            Some(Ok(config)) => config,
+            Some(Err(error)) => {
+                println!(
+                    "Keeping the old config, {} has an error: {}",
+                    watcher.path().display(),
+                    error
+                );
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+
+        let current = self.simulation.config();
+        let changes = current.changes(&config);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        println!("Reloaded {}:", watcher.path().display());
+        for change in changes {
+            println!("    {}", change);
         }
 
-        if self.time_to_create_bird < duration_since_start {
-            self.create_bird(arena_width, arena_height)?;
-            self.time_to_create_bird = duration_since_start + self.create_bird_every_miliseconds;
-            if self.birds_to_create_at_the_same_time < 50 {
-                self.birds_to_create_at_the_same_time += 1;
+        if config.bird_width != current.bird_width || config.bird_height != current.bird_height {
+            let arena = self.simulation.arena();
+            self.meshes = Meshes::new(context, arena.width(), &config)?;
+        }
+        self.simulation.set_config(config);
+        Ok(())
+    }
+
+    fn read_input(&self, context: &Context) -> Input {
+        let pressed_keys = keyboard::pressed_keys(context);
+        let mouse_location = mouse::position(context);
+
+        Input {
+            move_left: pressed_keys.contains(&KeyCode::A) || pressed_keys.contains(&KeyCode::Left),
+            move_right: pressed_keys.contains(&KeyCode::D)
+                || pressed_keys.contains(&KeyCode::Right),
+            jump: pressed_keys.contains(&KeyCode::Space),
+            firing: mouse::button_pressed(context, mouse::MouseButton::Left),
+            bomb: pressed_keys.contains(&KeyCode::B),
+            mouse_location: Point2::new(mouse_location.x, mouse_location.y),
+        }
+    }
+
+    fn update_playing(&mut self, context: &mut Context) {
+        let live_input = self.read_input(context);
+
+        while timer::check_update_time(context, TICKS_PER_SECOND) {
+            match &mut self.input_source {
+                InputSource::Live => self.simulation.tick(&live_input),
+                InputSource::Recording(replay, _path) => {
+                    replay.record(&live_input);
+                    self.simulation.tick(&live_input);
+                }
+                InputSource::Playback { replay, next_tick } => {
+                    // once the recording runs out the last frame stays on screen
+                    if let Some(input) = replay.input_for_tick(*next_tick) {
+                        self.simulation.tick(input);
+                        *next_tick += 1;
+                    }
+                }
+            }
+
+            if !self.simulation.still_alive() {
+                self.phase = if self.high_scores_path.is_some()
+                    && self.high_scores.qualifies(self.simulation.score())
+                {
+                    self.name.clear();
+                    GamePhase::EnterName
+                } else {
+                    GamePhase::GameOver
+                };
+                break;
             }
         }
+    }
+
+    /// Puts the finished run on the table under the name that was typed and saves it.
+    fn save_high_score(&mut self) {
+        let survived = self
+            .simulation
+            .game_over()
+            .map_or(0, |game_over| game_over.at);
+        let name = if self.name.trim().is_empty() {
+            "Anonymous"
+        } else {
+            &self.name
+        };
+        self.high_scores.insert(HighScore::new(
+            name,
+            self.simulation.score(),
+            survived,
+            self.simulation.seed(),
+        ));
 
-        if self.next_score_increase_time < duration_since_start {
-            let mut score_system = IncreaseScoreBySurvivingSystem;
-            score_system.run_now(&self.world);
-            self.next_score_increase_time =
-                duration_since_start + self.increase_score_every_miliseconds;
+        if let Some(path) = &self.high_scores_path {
+            if let Err(error) = self.high_scores.save(path) {
+                println!(
+                    "Couldn't save the high scores to {}: {}",
+                    path.display(),
+                    error
+                );
+            }
         }
+        self.phase = GamePhase::GameOver;
+    }
+
+    fn draw_world(&mut self, context: &mut Context) {
+        let mut draw_system = RenderSystem {
+            context,
+            meshes: &self.meshes,
+            duration_since_start: self.simulation.duration_since_start(),
+        };
+        draw_system.run_now(self.simulation.world());
+    }
+}
+
+impl EventHandler for GameState {
+    fn update(&mut self, context: &mut Context) -> GameResult<()> {
+        self.reload_config(context)?;
 
-        gravity_system.run_now(&self.world);
-        hit_ground.run_now(&self.world);
-        move_system.run_now(&self.world);
-        move_player_system.run_now(&self.world);
-        drag_system.run_now(&self.world);
-        check_egg.run_now(&self.world);
-        fly_system.run_now(&self.world);
-        landing_on_egg.run_now(&self.world);
-        reset_bullets.run_now(&self.world);
-        shoot_bird_system.run_now(&self.world);
-        hide_hit_bullets.run_now(&self.world);
-
-        // let still_alive = &self.world.fetch::<StillAlive>();
-
-        // if !still_alive.get() {
-        //     println!("game over");
-        // }
-        self.world.maintain();
+        match self.phase {
+            GamePhase::Playing => self.update_playing(context),
+            // keep the timer drained so un-pausing doesn't replay every missed tick at once
+            GamePhase::Title | GamePhase::Paused | GamePhase::EnterName | GamePhase::GameOver => {
+                while timer::check_update_time(context, TICKS_PER_SECOND) {}
+            }
+        }
         Ok(())
     }
 
     fn draw(&mut self, context: &mut Context) -> GameResult<()> {
         graphics::clear(context, graphics::BLACK);
 
-        let mut draw_system = RenderSystem { context };
-        draw_system.run_now(&self.world);
+        match self.phase {
+            GamePhase::Title => screens::draw_title(context)?,
+            GamePhase::Playing => self.draw_world(context),
+            GamePhase::Paused => {
+                self.draw_world(context);
+                screens::draw_paused(context)?;
+            }
+            GamePhase::EnterName | GamePhase::GameOver => {
+                self.draw_world(context);
+                screens::draw_game_over(
+                    context,
+                    &self.simulation.scorecard(),
+                    self.simulation.wave(),
+                    self.simulation.seed(),
+                    self.simulation.game_over(),
+                    self.simulation.stats(),
+                    &self.high_scores,
+                )?;
+                if self.phase == GamePhase::EnterName {
+                    screens::draw_name_entry(context, &self.name)?;
+                } else {
+                    screens::draw_play_again(context)?;
+                }
+            }
+        }
 
         graphics::present(context)
     }
+
+    fn key_down_event(
+        &mut self,
+        context: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if repeat {
+            return;
+        }
+
+        match (self.phase, keycode) {
+            (_, KeyCode::Escape) => event::quit(context),
+            (GamePhase::Title, KeyCode::Return) => self.phase = GamePhase::Playing,
+            (GamePhase::Playing, KeyCode::P) => self.phase = GamePhase::Paused,
+            (GamePhase::Paused, KeyCode::P) => self.phase = GamePhase::Playing,
+            (GamePhase::Paused, KeyCode::R) | (GamePhase::GameOver, KeyCode::R) => self.restart(),
+            (GamePhase::EnterName, KeyCode::Back) => {
+                self.name.pop();
+            }
+            (GamePhase::EnterName, KeyCode::Return) => self.save_high_score(),
+            _ => (),
+        }
+    }
+
+    fn text_input_event(&mut self, _context: &mut Context, character: char) {
+        if self.phase == GamePhase::EnterName
+            && !character.is_control()
+            && self.name.chars().count() < MAX_NAME_LENGTH
+        {
+            self.name.push(character);
+        }
+    }
 }
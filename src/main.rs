@@ -3,12 +3,22 @@ extern crate keep_it_alive;
 use ggez::conf::WindowMode;
 use ggez::event::{self};
 use ggez::ContextBuilder;
-use keep_it_alive::GameState;
+use keep_it_alive::resources::ArenaBounds;
+use keep_it_alive::{Config, ConfigWatcher, GameState, Replay};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
 
 fn main() {
+    let replay = read_argument("--replay").map(|path| read_replay(&PathBuf::from(path)));
+
     // Make a Context and an EventLoop.
-    let conf = WindowMode::default().dimensions(1024.0, 768.0);
-    let (mut ctx, mut event_loop) = ContextBuilder::new("game_name", "author_name")
+    // the window is exactly the size of the arena
+    let arena = ArenaBounds::default();
+    let conf = WindowMode::default().dimensions(arena.width(), arena.height());
+    let (mut ctx, mut event_loop) = ContextBuilder::new("keep_it_alive", "Brooks Patton")
         .window_mode(conf)
         .build()
         .unwrap();
@@ -16,11 +26,94 @@ fn main() {
     // Create an instance of your event handler.
     // Usually, you should provide it with the Context object
     // so it can load resources like images during setup.
-    let mut my_game = GameState::new(&mut ctx).unwrap();
+    let mut my_game = match replay {
+        Some(replay) => {
+            println!(
+                "Playing back {} ticks with seed {}",
+                replay.ticks(),
+                replay.seed()
+            );
+            GameState::playback(&mut ctx, replay).unwrap()
+        }
+        None => {
+            let (config, config_path) = read_config();
+            let seed = read_seed().unwrap_or_else(rand::random);
+            println!("Seed: {}", seed);
+            match read_argument("--record") {
+                Some(path) => {
+                    GameState::recording(&mut ctx, seed, PathBuf::from(path), config).unwrap()
+                }
+                None => {
+                    let mut game = GameState::new(&mut ctx, seed, config).unwrap();
+                    if let Some(path) = config_path {
+                        game.watch_config(ConfigWatcher::new(path));
+                    }
+                    game
+                }
+            }
+        }
+    };
 
     // Run!
     match event::run(&mut ctx, &mut event_loop, &mut my_game) {
         Ok(_) => println!("Exited cleanly."),
         Err(e) => println!("Error occured: {}", e),
     }
+
+    if let Err(error) = my_game.save_recording() {
+        println!("Could not save the replay: {}", error);
+    }
+}
+
+/// Returns the value following `name` on the command line, e.g. `--record run.replay`.
+fn read_argument(name: &str) -> Option<String> {
+    env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+/// Looks for `--seed <number>` on the command line so a run can be played again.
+fn read_seed() -> Option<u64> {
+    match read_argument("--seed") {
+        Some(seed) => match seed.parse() {
+            Ok(seed) => Some(seed),
+            Err(_) => {
+                println!("Ignoring invalid seed {}", seed);
+                None
+            }
+        },
+        None => None,
+    }
+}
+
+/// Loads `--config <path>`, or `config.toml` next to the game if there is one, and
+/// returns where it came from so the game can watch it for changes. A config that doesn't
+/// make sense stops the game from starting rather than being half applied.
+fn read_config() -> (Config, Option<PathBuf>) {
+    let path = match read_argument("--config") {
+        Some(path) => PathBuf::from(path),
+        None if Path::new(DEFAULT_CONFIG_PATH).exists() => PathBuf::from(DEFAULT_CONFIG_PATH),
+        None => return (Config::new(), None),
+    };
+
+    match Config::load(&path) {
+        Ok(config) => {
+            println!("Loaded config from {}", path.display());
+            (config, Some(path))
+        }
+        Err(error) => {
+            println!("Could not load {}: {}", path.display(), error);
+            process::exit(1);
+        }
+    }
+}
+
+/// Loads the replay to play back. There's nothing to show without it, so a file that
+/// can't be read stops the game from starting.
+fn read_replay(path: &Path) -> Replay {
+    match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(error) => {
+            println!("Could not load {}: {}", path.display(), error);
+            process::exit(1);
+        }
+    }
 }
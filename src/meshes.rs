@@ -1,7 +1,73 @@
+#![allow(non_snake_case)]
+
+use super::components::{MeshType, PowerUp, WingPose};
+use super::config::Config;
+use super::simulation::{
+    BULLET_SIZE, EGG_HEIGHT, EGG_WIDTH, FLOOR_HEIGHT, GRENADE_SIZE, PLAYER_HEIGHT, PLAYER_WIDTH,
+    POOP_SIZE, POWER_UP_SIZE,
+};
 use ggez::graphics::{DrawMode, Mesh, MeshBuilder, Rect};
 use ggez::nalgebra::Point2;
 use ggez::{graphics, Context, GameResult};
 
+/// Every mesh the game draws, built once up front so the simulation itself never needs a
+/// `Context`. Entities only carry a `MeshType` and the renderer looks the mesh up here.
+pub struct Meshes {
+    egg: Mesh,
+    player: Mesh,
+    floor: Mesh,
+    bird_poses: Vec<Mesh>,
+    bullet: Mesh,
+    poop: Mesh,
+    power_ups: Vec<Mesh>,
+    grenade: Mesh,
+    explosion: Mesh,
+}
+
+impl Meshes {
+    pub fn new(context: &mut Context, arena_width: f32, config: &Config) -> GameResult<Meshes> {
+        Ok(Meshes {
+            egg: createEggMesh(context, EGG_WIDTH, EGG_HEIGHT)?,
+            player: createPersonMesh(context, PLAYER_WIDTH, PLAYER_HEIGHT)?,
+            floor: createFloor(context, arena_width, FLOOR_HEIGHT)?,
+            bird_poses: WingPose::ALL
+                .iter()
+                .map(|wing_pose| {
+                    createBird(context, config.bird_width, config.bird_height, *wing_pose)
+                })
+                .collect::<GameResult<Vec<Mesh>>>()?,
+            bullet: createBullet(context, BULLET_SIZE)?,
+            poop: createPoop(context, POOP_SIZE)?,
+            power_ups: PowerUp::ALL
+                .iter()
+                .map(|power_up| createPowerUp(context, POWER_UP_SIZE, *power_up))
+                .collect::<GameResult<Vec<Mesh>>>()?,
+            grenade: createGrenade(context, GRENADE_SIZE)?,
+            explosion: createExplosion(context)?,
+        })
+    }
+
+    pub fn get(&self, mesh_type: MeshType) -> &Mesh {
+        match mesh_type {
+            MeshType::Egg => &self.egg,
+            MeshType::Player => &self.player,
+            MeshType::Floor => &self.floor,
+            MeshType::Bird(wing_pose) => {
+                let index = WingPose::ALL.iter().position(|p| *p == wing_pose).unwrap();
+                &self.bird_poses[index]
+            }
+            MeshType::Bullet => &self.bullet,
+            MeshType::Poop => &self.poop,
+            MeshType::PowerUp(power_up) => {
+                let index = PowerUp::ALL.iter().position(|p| *p == power_up).unwrap();
+                &self.power_ups[index]
+            }
+            MeshType::Grenade => &self.grenade,
+            MeshType::Explosion => &self.explosion,
+        }
+    }
+}
+
 pub fn createEggMesh(context: &mut Context, width: f32, height: f32) -> GameResult<Mesh> {
     MeshBuilder::new()
         .ellipse(
@@ -36,14 +102,26 @@ pub fn createFloor(context: &mut Context, width: f32, height: f32) -> GameResult
         .build(context)
 }
 
-pub fn createBird(context: &mut Context, width: f32, height: f32) -> GameResult<Mesh> {
+pub fn createBird(
+    context: &mut Context,
+    width: f32,
+    height: f32,
+    wing_pose: WingPose,
+) -> GameResult<Mesh> {
+    let (wing_span, wing_tip) = match wing_pose {
+        WingPose::Up => (width, -height),
+        WingPose::Level => (width, -height * 0.2),
+        WingPose::Down => (width, height * 0.6),
+        WingPose::Tucked => (width * 0.4, -height * 1.2),
+    };
+
     MeshBuilder::new()
         .polyline(
             DrawMode::stroke(5.0),
             &[
-                Point2::new(-width, -height),
+                Point2::new(-wing_span, wing_tip),
                 Point2::new(0.0, 0.0),
-                Point2::new(width, -height),
+                Point2::new(wing_span, wing_tip),
             ],
             graphics::WHITE,
         )?
@@ -61,3 +139,55 @@ pub fn createBullet(context: &mut Context, radius: f32) -> GameResult<Mesh> {
         )
         .build(context)
 }
+
+pub fn createPoop(context: &mut Context, radius: f32) -> GameResult<Mesh> {
+    MeshBuilder::new()
+        .circle(
+            DrawMode::fill(),
+            Point2::new(0.0, 0.0),
+            radius,
+            0.1,
+            graphics::Color::new(0.55, 0.4, 0.2, 1.0),
+        )
+        .build(context)
+}
+
+pub fn createPowerUp(context: &mut Context, size: f32, power_up: PowerUp) -> GameResult<Mesh> {
+    let color = match power_up {
+        PowerUp::MachineGun => graphics::Color::new(1.0, 0.3, 0.3, 1.0),
+        PowerUp::ExtraBullet => graphics::Color::new(1.0, 1.0, 0.3, 1.0),
+        PowerUp::ClearScreen => graphics::Color::new(0.3, 0.6, 1.0, 1.0),
+        PowerUp::GrenadeLauncher => graphics::Color::new(0.3, 1.0, 0.3, 1.0),
+    };
+    let body = Rect::new(-size / 2.0, -size / 2.0, size, size);
+
+    MeshBuilder::new()
+        .rectangle(DrawMode::fill(), body, color)
+        .rectangle(DrawMode::stroke(2.0), body, graphics::WHITE)
+        .build(context)
+}
+
+pub fn createGrenade(context: &mut Context, radius: f32) -> GameResult<Mesh> {
+    MeshBuilder::new()
+        .circle(
+            DrawMode::fill(),
+            Point2::new(0.0, 0.0),
+            radius,
+            0.1,
+            graphics::Color::new(0.2, 0.5, 0.2, 1.0),
+        )
+        .build(context)
+}
+
+/// A circle of radius 1, scaled up to the size of the blast when it is drawn.
+pub fn createExplosion(context: &mut Context) -> GameResult<Mesh> {
+    MeshBuilder::new()
+        .circle(
+            DrawMode::fill(),
+            Point2::new(0.0, 0.0),
+            1.0,
+            0.001,
+            graphics::Color::new(1.0, 0.6, 0.1, 0.6),
+        )
+        .build(context)
+}
@@ -0,0 +1,228 @@
+use super::config::Config;
+use super::resources::ArenaBounds;
+use super::simulation::{Input, TICKS_PER_SECOND};
+use ggez::nalgebra::Point2;
+use ggez::{GameError, GameResult};
+use std::fs;
+use std::path::Path;
+
+const HEADER: &str = "keep_it_alive replay";
+// bump whenever the file layout or the rules change, since an older replay would drift
+// from what was really played
+const VERSION: u32 = 4;
+const CONFIG_START: &str = "config";
+const CONFIG_END: &str = "end config";
+
+/// Everything needed to play a run back exactly: the seed, the arena and config the
+/// simulation was created with and the input that was fed to every tick, in order.
+///
+/// Saved as plain text, with the config as a block of TOML and then one tick per line, so a
+/// replay attached to a bug report can be read and trimmed by hand.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    seed: u64,
+    arena: ArenaBounds,
+    config: Config,
+    inputs: Vec<Input>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, arena: ArenaBounds, config: Config) -> Replay {
+        Replay {
+            seed,
+            arena,
+            config,
+            inputs: vec![],
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn arena(&self) -> ArenaBounds {
+        self.arena
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn record(&mut self, input: &Input) {
+        self.inputs.push(input.clone());
+    }
+
+    pub fn input_for_tick(&self, tick: usize) -> Option<&Input> {
+        self.inputs.get(tick)
+    }
+
+    pub fn save(&self, path: &Path) -> GameResult<()> {
+        let mut text = format!(
+            "{} {}\nseed {}\narena {} {}\nticks_per_second {}\n",
+            HEADER,
+            VERSION,
+            self.seed,
+            self.arena.width(),
+            self.arena.height(),
+            TICKS_PER_SECOND
+        );
+        let config = toml::to_string(&self.config)
+            .map_err(|error| GameError::ResourceLoadError(error.to_string()))?;
+        text.push_str(&format!("{}\n{}{}\n", CONFIG_START, config, CONFIG_END));
+        for input in &self.inputs {
+            text.push_str(&format!(
+                "{}{}{}{}{} {} {}\n",
+                flag(input.move_left),
+                flag(input.move_right),
+                flag(input.jump),
+                flag(input.firing),
+                flag(input.bomb),
+                input.mouse_location.x,
+                input.mouse_location.y
+            ));
+        }
+
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> GameResult<Replay> {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+
+        let version: u32 = match lines.next().and_then(|line| line.strip_prefix(HEADER)) {
+            Some(version) => parse_number(Some(version.trim()))?,
+            None => return Err(invalid("not a replay file")),
+        };
+        if version != VERSION {
+            return Err(invalid(&format!(
+                "recorded by a different version of the game, format {} instead of {}",
+                version, VERSION
+            )));
+        }
+        let seed = parse_setting(lines.next(), "seed")?;
+        let mut arena = lines.next().unwrap_or_default().split(' ');
+        if arena.next() != Some("arena") {
+            return Err(invalid("missing arena size"));
+        }
+        let arena = ArenaBounds::new(parse_number(arena.next())?, parse_number(arena.next())?);
+        let ticks_per_second: u32 = parse_setting(lines.next(), "ticks_per_second")?;
+        if ticks_per_second != TICKS_PER_SECOND {
+            return Err(invalid(&format!(
+                "recorded at {} ticks per second but the game runs at {}",
+                ticks_per_second, TICKS_PER_SECOND
+            )));
+        }
+
+        if lines.next() != Some(CONFIG_START) {
+            return Err(invalid("missing config"));
+        }
+        let config_text: Vec<&str> = lines
+            .by_ref()
+            .take_while(|line| *line != CONFIG_END)
+            .collect();
+        let config: Config =
+            toml::from_str(&config_text.join("\n")).map_err(|error| invalid(&error.to_string()))?;
+        config.validate()?;
+
+        let mut replay = Replay::new(seed, arena, config);
+        for line in lines {
+            replay.record(&parse_input(line)?);
+        }
+        Ok(replay)
+    }
+}
+
+fn flag(value: bool) -> char {
+    if value {
+        '1'
+    } else {
+        '0'
+    }
+}
+
+fn parse_input(line: &str) -> GameResult<Input> {
+    let mut words = line.split(' ');
+    let flags: Vec<char> = words.next().unwrap_or_default().chars().collect();
+    if flags.len() != 5 || flags.iter().any(|flag| *flag != '0' && *flag != '1') {
+        return Err(invalid(&format!("bad input line '{}'", line)));
+    }
+    let x = parse_number(words.next())?;
+    let y = parse_number(words.next())?;
+
+    Ok(Input {
+        move_left: flags[0] == '1',
+        move_right: flags[1] == '1',
+        jump: flags[2] == '1',
+        firing: flags[3] == '1',
+        bomb: flags[4] == '1',
+        mouse_location: Point2::new(x, y),
+    })
+}
+
+fn parse_setting<T: std::str::FromStr>(line: Option<&str>, name: &str) -> GameResult<T> {
+    let mut words = line.unwrap_or_default().split(' ');
+    if words.next() != Some(name) {
+        return Err(invalid(&format!("missing {}", name)));
+    }
+    parse_number(words.next())
+}
+
+fn parse_number<T: std::str::FromStr>(word: Option<&str>) -> GameResult<T> {
+    word.and_then(|word| word.parse().ok())
+        .ok_or_else(|| invalid(&format!("expected a number, found {:?}", word)))
+}
+
+fn invalid(reason: &str) -> GameError {
+    GameError::ResourceLoadError(format!("invalid replay: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("keep_it_alive_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn round_trip_keeps_every_input() {
+        let path = temp_path("round_trip.replay");
+        let mut replay = Replay::new(7, ArenaBounds::default(), Config::new());
+        let mut input = Input::new();
+        replay.record(&input);
+        input.move_left = true;
+        input.bomb = true;
+        input.mouse_location = Point2::new(12.5, 300.0);
+        replay.record(&input);
+        replay.save(&path).unwrap();
+
+        let loaded = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.seed(), 7);
+        assert_eq!(loaded.arena(), ArenaBounds::default());
+        assert_eq!(loaded.ticks(), 2);
+        let last = loaded.input_for_tick(1).unwrap();
+        assert!(last.move_left && last.bomb && !last.firing);
+        assert_eq!(last.mouse_location, Point2::new(12.5, 300.0));
+    }
+
+    #[test]
+    fn rejects_older_formats() {
+        let path = temp_path("old_format.replay");
+        fs::write(
+            &path,
+            "keep_it_alive replay 1\nseed 1\narena 1024 768\nticks_per_second 60\n0000 0 0\n",
+        )
+        .unwrap();
+
+        let result = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
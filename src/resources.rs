@@ -1,31 +1,93 @@
+use super::species::Species;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use specs::Entity;
+
+/// Alive until the egg is lost, after which it remembers how.
 #[derive(Default)]
-pub struct StillAlive(bool);
+pub struct StillAlive(Option<GameOver>);
 
 impl StillAlive {
     pub fn new() -> StillAlive {
-        StillAlive(true)
+        StillAlive(None)
     }
 
     pub fn get(&self) -> bool {
+        self.0.is_none()
+    }
+
+    pub fn game_over(&self) -> Option<GameOver> {
         self.0
     }
 
-    pub fn set(&mut self, new_value: bool) {
-        self.0 = new_value;
+    /// Ends the run. Only the first way the egg was lost counts.
+    pub fn end(&mut self, game_over: GameOver) {
+        if self.0.is_none() {
+            self.0 = Some(game_over);
+        }
+    }
+}
+
+/// How the egg was lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOverReason {
+    /// A bird carried it off the screen.
+    EggStolen,
+    /// It was dropped and nobody caught it.
+    EggBroken,
+}
+
+/// Why and when a run ended, for the recap screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameOver {
+    pub reason: GameOverReason,
+    /// The last bird to grab the egg, if one ever did.
+    pub species: Option<Species>,
+    /// Milliseconds into the game.
+    pub at: u128,
+}
+
+impl GameOver {
+    pub fn describe(&self) -> String {
+        match (self.reason, self.species) {
+            (GameOverReason::EggStolen, Some(species)) => {
+                format!("A {} flew off with the egg", species.name())
+            }
+            (GameOverReason::EggStolen, None) => "The egg was carried away".to_string(),
+            (GameOverReason::EggBroken, Some(species)) => {
+                format!("The egg broke after a {} dropped it", species.name())
+            }
+            (GameOverReason::EggBroken, None) => "The egg fell and broke".to_string(),
+        }
     }
 }
 
+/// Running totals shown on the recap screen.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunStats {
+    pub birds_killed: usize,
+    /// Bullets and grenades.
+    pub shots_fired: usize,
+}
+
+/// How many bullets the player owns. Only ever grows, up to the configured maximum.
 #[derive(Default)]
-pub struct BulletSize(f32);
+pub struct BulletPoolSize(usize);
 
-impl BulletSize {
-    pub fn new(size: f32) -> BulletSize {
-        BulletSize(size)
+impl BulletPoolSize {
+    pub fn new(size: usize) -> BulletPoolSize {
+        BulletPoolSize(size)
     }
 
-    pub fn get(&self) -> f32 {
+    pub fn get(&self) -> usize {
         self.0
     }
+
+    pub fn grow(&mut self, max: usize) {
+        if self.0 < max {
+            self.0 += 1;
+        }
+    }
 }
 
 #[derive(Default)]
@@ -45,19 +107,71 @@ impl DelayFiringUntilAfter {
     }
 }
 
-#[derive(Default)]
-pub struct Score(usize);
+/// The playfield, from the top left corner at (0, 0) down to the floor at the bottom
+/// right. Anything that needs to know where the edges are reads them from here.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArenaBounds {
+    width: f32,
+    height: f32,
+}
 
-impl Score {
-    pub fn new() -> Score {
-        Score(0)
+impl ArenaBounds {
+    pub fn new(width: f32, height: f32) -> ArenaBounds {
+        ArenaBounds { width, height }
     }
 
-    pub fn get(&self) -> usize {
-        self.0
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Also where the floor is, since the floor runs along the bottom edge.
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+}
+
+/// The size of the window the game opens.
+impl Default for ArenaBounds {
+    fn default() -> ArenaBounds {
+        ArenaBounds::new(1024.0, 768.0)
+    }
+}
+
+/// The only source of randomness in the game. Everything random has to draw from here so
+/// that a run can be replayed from its seed.
+pub struct SeededRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
     }
 
-    pub fn increase(&mut self, amount: usize) {
-        self.0 += amount;
+    pub fn seed(&self) -> u64 {
+        self.seed
     }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
+
+impl Default for SeededRng {
+    fn default() -> SeededRng {
+        SeededRng::new(0)
+    }
+}
+
+/// Sent on the `EventChannel<Collision>` resource whenever `entity` overlaps `other` and
+/// `entity`'s collider mask includes `other`'s layer. If both care about each other there
+/// are two events, one each way round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collision {
+    pub entity: Entity,
+    pub other: Entity,
 }
@@ -0,0 +1,228 @@
+use super::config::Config;
+
+/// Where points came from, for the breakdown on the game over screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreCategory {
+    /// Each bird's own points.
+    Kills,
+    /// What the combo multiplier added on top of `Kills`.
+    Combo,
+    /// Extra for every bird after the first caught in the same grenade blast.
+    Blasts,
+    MidAir,
+    EggCarriers,
+    Survival,
+    Waves,
+}
+
+impl ScoreCategory {
+    pub const ALL: [ScoreCategory; 7] = [
+        ScoreCategory::Kills,
+        ScoreCategory::Combo,
+        ScoreCategory::Blasts,
+        ScoreCategory::MidAir,
+        ScoreCategory::EggCarriers,
+        ScoreCategory::Survival,
+        ScoreCategory::Waves,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScoreCategory::Kills => "Birds",
+            ScoreCategory::Combo => "Combos",
+            ScoreCategory::Blasts => "Blasts",
+            ScoreCategory::MidAir => "Mid-air kills",
+            ScoreCategory::EggCarriers => "Egg rescues",
+            ScoreCategory::Survival => "Survival",
+            ScoreCategory::Waves => "Waves cleared",
+        }
+    }
+
+    fn index(&self) -> usize {
+        ScoreCategory::ALL
+            .iter()
+            .position(|category| category == self)
+            .unwrap()
+    }
+}
+
+/// A bird that was shot or blown up, and everything about it that is worth points.
+#[derive(Debug, Clone, Copy)]
+pub struct Kill {
+    /// The species' own points.
+    pub points: usize,
+    /// Which bird this was in its grenade blast, counting from 1. 0 for bullets.
+    pub blast_kills: usize,
+    pub mid_air: bool,
+    pub carrying_egg: bool,
+}
+
+/// The running score, where it came from, and the current kill streak. Kills in quick
+/// succession build a combo that multiplies each bird's points, and the multiplier drops
+/// a level every `combo_window` that passes without one.
+#[derive(Debug, Clone, Default)]
+pub struct Score {
+    total: usize,
+    breakdown: [usize; ScoreCategory::ALL.len()],
+    streak: usize,
+    best_streak: usize,
+    combo_ends_at: u128,
+}
+
+impl Score {
+    pub fn new() -> Score {
+        Score::default()
+    }
+
+    pub fn get(&self) -> usize {
+        self.total
+    }
+
+    pub fn add(&mut self, category: ScoreCategory, points: usize) {
+        self.total += points;
+        self.breakdown[category.index()] += points;
+    }
+
+    /// Points from each category, in the order of `ScoreCategory::ALL`.
+    pub fn breakdown(&self) -> Vec<(ScoreCategory, usize)> {
+        ScoreCategory::ALL
+            .iter()
+            .map(|category| (*category, self.breakdown[category.index()]))
+            .collect()
+    }
+
+    pub fn best_streak(&self) -> usize {
+        self.best_streak
+    }
+
+    pub fn multiplier(&self, config: &Config) -> usize {
+        (1 + self.streak / config.kills_per_combo_level).min(config.max_combo_multiplier)
+    }
+
+    /// Scores a kill at the current multiplier and adds it to the streak. Returns how many
+    /// points it was worth altogether.
+    pub fn kill(&mut self, kill: &Kill, duration_since_start: u128, config: &Config) -> usize {
+        let before = self.total;
+        let multiplier = self.multiplier(config);
+
+        self.add(ScoreCategory::Kills, kill.points);
+        self.add(ScoreCategory::Combo, kill.points * (multiplier - 1));
+        // every extra bird caught in the same blast is worth more than the last
+        self.add(
+            ScoreCategory::Blasts,
+            kill.points * kill.blast_kills.saturating_sub(1),
+        );
+        if kill.mid_air {
+            self.add(ScoreCategory::MidAir, config.mid_air_bonus);
+        }
+        if kill.carrying_egg {
+            self.add(ScoreCategory::EggCarriers, config.egg_carrier_bonus);
+        }
+
+        self.streak += 1;
+        self.best_streak = self.best_streak.max(self.streak);
+        self.combo_ends_at = duration_since_start + config.combo_window;
+        self.total - before
+    }
+
+    /// Drops the multiplier a level for every `combo_window` since the last kill, or since
+    /// it last dropped.
+    pub fn decay_combo(&mut self, duration_since_start: u128, config: &Config) {
+        while self.streak > 0 && self.combo_ends_at < duration_since_start {
+            let level = self.streak / config.kills_per_combo_level;
+            self.streak = level.saturating_sub(1) * config.kills_per_combo_level;
+            self.combo_ends_at += config.combo_window;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        let mut config = Config::new();
+        config.kills_per_combo_level = 2;
+        config.max_combo_multiplier = 3;
+        config.combo_window = 1000;
+        config.mid_air_bonus = 5;
+        config.egg_carrier_bonus = 50;
+        config
+    }
+
+    fn kill(points: usize) -> Kill {
+        Kill {
+            points,
+            blast_kills: 0,
+            mid_air: false,
+            carrying_egg: false,
+        }
+    }
+
+    #[test]
+    fn streaks_raise_the_multiplier_up_to_the_cap() {
+        let config = config();
+        let mut score = Score::new();
+        let earned: Vec<usize> = (0..8)
+            .map(|kill_number| score.kill(&kill(10), kill_number * 100, &config))
+            .collect();
+
+        assert_eq!(earned, vec![10, 10, 20, 20, 30, 30, 30, 30]);
+        assert_eq!(score.get(), 180);
+        assert_eq!(score.best_streak(), 8);
+        assert_eq!(score.multiplier(&config), 3);
+    }
+
+    #[test]
+    fn bonuses_land_in_their_own_categories() {
+        let config = config();
+        let mut score = Score::new();
+        let earned = score.kill(
+            &Kill {
+                points: 10,
+                blast_kills: 3,
+                mid_air: true,
+                carrying_egg: true,
+            },
+            0,
+            &config,
+        );
+
+        assert_eq!(earned, 10 + 20 + 5 + 50);
+        assert_eq!(score.get(), earned);
+        let breakdown = score.breakdown();
+        let points = |category| {
+            breakdown
+                .iter()
+                .find(|(other, _points)| *other == category)
+                .unwrap()
+                .1
+        };
+        assert_eq!(points(ScoreCategory::Kills), 10);
+        assert_eq!(points(ScoreCategory::Combo), 0);
+        assert_eq!(points(ScoreCategory::Blasts), 20);
+        assert_eq!(points(ScoreCategory::MidAir), 5);
+        assert_eq!(points(ScoreCategory::EggCarriers), 50);
+    }
+
+    #[test]
+    fn the_combo_drops_a_level_per_window_without_a_kill() {
+        let config = config();
+        let mut score = Score::new();
+        for kill_number in 0..5 {
+            score.kill(&kill(10), kill_number, &config);
+        }
+        assert_eq!(score.multiplier(&config), 3);
+
+        // still inside the window of the last kill
+        score.decay_combo(1004, &config);
+        assert_eq!(score.multiplier(&config), 3);
+
+        score.decay_combo(1005, &config);
+        assert_eq!(score.multiplier(&config), 2);
+
+        score.decay_combo(3005, &config);
+        assert_eq!(score.multiplier(&config), 1);
+        assert_eq!(score.best_streak(), 5);
+    }
+}
@@ -0,0 +1,183 @@
+use super::high_scores::HighScores;
+use super::resources::{GameOver, RunStats};
+use super::scoring::Score;
+use ggez::nalgebra::Point2;
+use ggez::{graphics, Context, GameResult};
+
+fn draw_text(
+    context: &mut Context,
+    text: &str,
+    size: f32,
+    location: Point2<f32>,
+) -> GameResult<()> {
+    let mut text = graphics::Text::new(text);
+    let font = graphics::Font::default();
+    let font_scale = graphics::Scale::uniform(size);
+
+    text.set_font(font, font_scale);
+    graphics::draw(
+        context,
+        &text,
+        graphics::DrawParam::default().dest(location),
+    )
+}
+
+pub fn draw_title(context: &mut Context) -> GameResult<()> {
+    let (arena_width, arena_height) = graphics::drawable_size(context);
+
+    draw_text(
+        context,
+        "Keep it Alive",
+        100.0,
+        Point2::new(arena_width / 5.0, arena_height / 2.0 - 100.0),
+    )?;
+    draw_text(
+        context,
+        "Press Enter to start",
+        30.0,
+        Point2::new(arena_width / 5.0, arena_height / 2.0 + 50.0),
+    )
+}
+
+pub fn draw_paused(context: &mut Context) -> GameResult<()> {
+    let (arena_width, arena_height) = graphics::drawable_size(context);
+
+    draw_text(
+        context,
+        "Paused",
+        100.0,
+        Point2::new(arena_width / 3.0, arena_height / 2.0 - 100.0),
+    )?;
+    draw_text(
+        context,
+        "Press P to continue or R to restart",
+        30.0,
+        Point2::new(arena_width / 3.0, arena_height / 2.0 + 50.0),
+    )
+}
+
+/// Minutes and seconds, like 2:05.
+fn format_duration(milliseconds: u128) -> String {
+    let seconds = milliseconds / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// The recap once the egg is lost: what happened, the score, where it came from and how
+/// the run went, with the high score table beside it.
+pub fn draw_game_over(
+    context: &mut Context,
+    score: &Score,
+    wave: usize,
+    seed: u64,
+    game_over: Option<GameOver>,
+    stats: RunStats,
+    high_scores: &HighScores,
+) -> GameResult<()> {
+    let (arena_width, arena_height) = graphics::drawable_size(context);
+    let left = arena_width / 10.0;
+    let right = arena_width * 0.55;
+
+    draw_text(
+        context,
+        "Game Over",
+        100.0,
+        Point2::new(arena_width / 4.0, arena_height / 2.0 - 300.0),
+    )?;
+    if let Some(game_over) = game_over {
+        draw_text(
+            context,
+            &game_over.describe(),
+            24.0,
+            Point2::new(left, arena_height / 2.0 - 160.0),
+        )?;
+    }
+    draw_text(
+        context,
+        &format!("You Scored {}", score.get()),
+        50.0,
+        Point2::new(left, arena_height / 2.0 - 120.0),
+    )?;
+    let survived = game_over.map_or(0, |game_over| game_over.at);
+    let recap = [
+        format!("Survived {}", format_duration(survived)),
+        format!("Birds killed: {}", stats.birds_killed),
+        format!("Shots fired: {}", stats.shots_fired),
+        format!("Best streak: {}", score.best_streak()),
+        format!("Reached wave {}", wave),
+        format!("Seed: {}", seed),
+    ];
+    for (line, text) in recap.iter().enumerate() {
+        draw_text(
+            context,
+            text,
+            20.0,
+            Point2::new(left, arena_height / 2.0 - 30.0 + line as f32 * 25.0),
+        )?;
+    }
+    let breakdown = score
+        .breakdown()
+        .into_iter()
+        .filter(|(_category, points)| *points > 0);
+    for (line, (category, points)) in breakdown.enumerate() {
+        draw_text(
+            context,
+            &format!("{}: {}", category.name(), points),
+            18.0,
+            Point2::new(left + 20.0, arena_height / 2.0 + 150.0 + line as f32 * 20.0),
+        )?;
+    }
+
+    draw_text(
+        context,
+        "High Scores",
+        30.0,
+        Point2::new(right, arena_height / 2.0 - 160.0),
+    )?;
+    if high_scores.entries().is_empty() {
+        draw_text(
+            context,
+            "Nobody yet",
+            18.0,
+            Point2::new(right, arena_height / 2.0 - 120.0),
+        )?;
+    }
+    for (rank, entry) in high_scores.entries().iter().enumerate() {
+        draw_text(
+            context,
+            &format!(
+                "{}. {}  {}  {}  {}",
+                rank + 1,
+                entry.name,
+                entry.score,
+                format_duration(entry.survived),
+                entry.date
+            ),
+            18.0,
+            Point2::new(right, arena_height / 2.0 - 120.0 + rank as f32 * 24.0),
+        )?;
+    }
+    Ok(())
+}
+
+pub fn draw_play_again(context: &mut Context) -> GameResult<()> {
+    let (arena_width, arena_height) = graphics::drawable_size(context);
+
+    draw_text(
+        context,
+        "Press R to play again",
+        30.0,
+        Point2::new(arena_width / 10.0, arena_height - 60.0),
+    )
+}
+
+/// Asks for a name to put next to a score that made the table.
+pub fn draw_name_entry(context: &mut Context, name: &str) -> GameResult<()> {
+    let (arena_width, arena_height) = graphics::drawable_size(context);
+
+    draw_text(
+        context,
+        &format!("New high score! Enter your name: {}_", name),
+        30.0,
+        Point2::new(arena_width / 10.0, arena_height - 60.0),
+    )
+}
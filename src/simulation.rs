@@ -0,0 +1,525 @@
+use super::components::{
+    Acceleration, ActiveEffects, Animation, BirdSpecies, Bombs, Bullet, BulletState, CarriedBy,
+    CarryingEgg, Collider, DelayPoopUntilAfter, Drag, EdgeBehaviour, Explosion, Falling,
+    FloatingText, Floor, Flyer, Grenade, HasGravity, Height, HitPoints, KeepAlive, KeepInBounds,
+    Layers, Lifetime, MeshType, ObjectMesh, OnGround, Pickup, Player, Poop, Position, Shape,
+    StolenBy, StunnedUntil, TemporaryBullet, Velocity, Width, WingPose,
+};
+use super::config::Config;
+use super::resources::{
+    ArenaBounds, BulletPoolSize, Collision, DelayFiringUntilAfter, GameOver, RunStats, SeededRng,
+    StillAlive,
+};
+use super::scoring::{Score, ScoreCategory};
+use super::spatial_grid::{SpatialGrid, DEFAULT_CELL_SIZE};
+use super::species::Species;
+use super::systems::{
+    build_popup, AnimationSystem, ApplyForceSystem, BulletPoolSystem, CatchEggSystem,
+    CheckEggSystem, ClearScreenSystem, CollectPickupsSystem, DecayComboSystem,
+    DetectCollisionsSystem, DetonateGrenadesSystem, DragSystem, DropPoopSystem,
+    ExpireEffectsSystem, ExpireLifetimesSystem, FireBulletSystem, FlapWingsSystem, FlySystem,
+    FollowCarrierSystem, GrabEggSystem, GravitySystem, HideHitBullets, HitByPoopSystem, HitGround,
+    IncreaseScoreBySurvivingSystem, KeepBirdsAboveFloorSystem, KeepInBoundsSystem,
+    LaunchGrenadeSystem, MovePlayerSystem, RecoverFromStunSystem, ResetBulletsSystem,
+    ShootBirdsSystem, UpdateExplosionsSystem, UpdateSpatialGridSystem,
+};
+use super::waves::{SpawnEdge, WaveDirector};
+use ggez::graphics;
+use ggez::nalgebra::Point2;
+use rand::prelude::*;
+use specs::prelude::*;
+use specs::shrev::{EventChannel, ReaderId};
+pub const EGG_WIDTH: f32 = 5.0;
+pub const EGG_HEIGHT: f32 = 15.0;
+pub const PLAYER_WIDTH: f32 = 15.0;
+pub const PLAYER_HEIGHT: f32 = 50.0;
+pub const FLOOR_HEIGHT: f32 = 5.0;
+pub const BULLET_SIZE: f32 = 5.0;
+pub const POOP_SIZE: f32 = 4.0;
+pub const POWER_UP_SIZE: f32 = 12.0;
+pub const GRENADE_SIZE: f32 = 6.0;
+pub const TICKS_PER_SECOND: u32 = 60;
+pub const DELTA_TIME: f32 = 1.0 / TICKS_PER_SECOND as f32;
+
+/// Everything the player can do in a single update, independent of where it came from.
+#[derive(Debug, Clone)]
+pub struct Input {
+    pub move_left: bool,
+    pub move_right: bool,
+    pub jump: bool,
+    pub firing: bool,
+    pub bomb: bool,
+    pub mouse_location: Point2<f32>,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input {
+            move_left: false,
+            move_right: false,
+            jump: false,
+            firing: false,
+            bomb: false,
+            mouse_location: Point2::new(0.0, 0.0),
+        }
+    }
+}
+
+impl Default for Input {
+    fn default() -> Input {
+        Input::new()
+    }
+}
+
+/// The game world and all of the gameplay systems, with no dependency on a window or
+/// graphics context.
+pub struct Simulation {
+    world: World,
+    ticks: u64,
+    bomb_held: bool,
+    next_score_increase_time: u128,
+    shoot_birds_collisions: ReaderId<Collision>,
+    grenade_collisions: ReaderId<Collision>,
+    poop_collisions: ReaderId<Collision>,
+    pickup_collisions: ReaderId<Collision>,
+    grab_egg_collisions: ReaderId<Collision>,
+    catch_egg_collisions: ReaderId<Collision>,
+}
+
+impl Simulation {
+    pub fn new(arena: ArenaBounds, seed: u64, config: Config) -> Simulation {
+        let player_drag = config.player_drag;
+        let next_score_increase_time = config.survival_score_interval;
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<ObjectMesh>();
+        world.register::<HasGravity>();
+        world.register::<Floor>();
+        world.register::<Height>();
+        world.register::<Velocity>();
+        world.register::<Acceleration>();
+        world.register::<Drag>();
+        world.register::<OnGround>();
+        world.register::<Width>();
+        world.register::<KeepAlive>();
+        world.register::<Flyer>();
+        world.register::<Player>();
+        world.register::<Bullet>();
+        world.register::<BulletState>();
+        world.register::<CarriedBy>();
+        world.register::<CarryingEgg>();
+        world.register::<Falling>();
+        world.register::<Poop>();
+        world.register::<DelayPoopUntilAfter>();
+        world.register::<StunnedUntil>();
+        world.register::<Pickup>();
+        world.register::<ActiveEffects>();
+        world.register::<TemporaryBullet>();
+        world.register::<Grenade>();
+        world.register::<Explosion>();
+        world.register::<Bombs>();
+        world.register::<Animation>();
+        world.register::<BirdSpecies>();
+        world.register::<HitPoints>();
+        world.register::<Collider>();
+        world.register::<KeepInBounds>();
+        world.register::<StolenBy>();
+        world.register::<FloatingText>();
+        world.register::<Lifetime>();
+
+        world.insert(StillAlive::new());
+        world.insert(RunStats::default());
+        world.insert(DelayFiringUntilAfter::new());
+        world.insert(Score::new());
+        world.insert(SeededRng::new(seed));
+        world.insert(BulletPoolSize::new(config.starting_bullets));
+        world.insert(WaveDirector::new());
+        world.insert(SpatialGrid::new(DEFAULT_CELL_SIZE));
+        let mut collisions = EventChannel::<Collision>::new();
+        let shoot_birds_collisions = collisions.register_reader();
+        let grenade_collisions = collisions.register_reader();
+        let poop_collisions = collisions.register_reader();
+        let pickup_collisions = collisions.register_reader();
+        let grab_egg_collisions = collisions.register_reader();
+        let catch_egg_collisions = collisions.register_reader();
+        world.insert(collisions);
+        world.insert(arena);
+        world.insert(config);
+
+        // egg
+        world
+            .create_entity()
+            .with(Position {
+                x: arena.width() / 2.0,
+                y: arena.height() - 25.0,
+            })
+            .with(ObjectMesh::new(MeshType::Egg))
+            .with(Width::new(EGG_WIDTH))
+            .with(Height::new(EGG_HEIGHT))
+            .with(Collider::rectangle(
+                EGG_WIDTH,
+                EGG_HEIGHT,
+                Layers::EGG,
+                Layers::PLAYER | Layers::BIRD,
+            ))
+            .with(Velocity { x: 0.0, y: 0.0 })
+            .with(Acceleration { x: 0.0, y: 0.0 })
+            .with(KeepAlive::new())
+            .build();
+
+        // player
+        world
+            .create_entity()
+            .with(Position {
+                x: 100.0,
+                y: arena.height() - PLAYER_WIDTH - 500.0,
+            })
+            .with(ObjectMesh::new(MeshType::Player))
+            .with(HasGravity)
+            .with(Height::new(PLAYER_HEIGHT / 2.0))
+            .with(Width::new(PLAYER_WIDTH))
+            .with(Collider::rectangle(
+                PLAYER_WIDTH,
+                PLAYER_HEIGHT / 2.0,
+                Layers::PLAYER,
+                Layers::NONE,
+            ))
+            .with(Velocity { x: 0.0, y: 0.0 })
+            .with(Acceleration { x: 0.0, y: 0.0 })
+            .with(Drag::new(player_drag))
+            .with(KeepInBounds::new(EdgeBehaviour::Clamp))
+            .with(OnGround::new())
+            .with(Player)
+            .with(ActiveEffects::new())
+            .with(Bombs::new())
+            .build();
+
+        // floor
+        world
+            .create_entity()
+            .with(Position {
+                x: 0.0,
+                y: arena.height() - FLOOR_HEIGHT,
+            })
+            .with(ObjectMesh::new(MeshType::Floor))
+            .with(Floor)
+            .build();
+
+        // bullets
+        let mut bullet_pool = BulletPoolSystem;
+        bullet_pool.run_now(&world);
+
+        Simulation {
+            world,
+            ticks: 0,
+            bomb_held: false,
+            next_score_increase_time,
+            shoot_birds_collisions,
+            grenade_collisions,
+            poop_collisions,
+            pickup_collisions,
+            grab_egg_collisions,
+            catch_egg_collisions,
+        }
+    }
+
+    pub fn arena(&self) -> ArenaBounds {
+        *self.world.fetch::<ArenaBounds>()
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn still_alive(&self) -> bool {
+        self.world.fetch::<StillAlive>().get()
+    }
+
+    /// How the run ended, once it has.
+    pub fn game_over(&self) -> Option<GameOver> {
+        self.world.fetch::<StillAlive>().game_over()
+    }
+
+    pub fn stats(&self) -> RunStats {
+        *self.world.fetch::<RunStats>()
+    }
+
+    pub fn score(&self) -> usize {
+        self.world.fetch::<Score>().get()
+    }
+
+    /// The score along with where it came from and the current combo.
+    pub fn scorecard(&self) -> Score {
+        (*self.world.fetch::<Score>()).clone()
+    }
+
+    pub fn wave(&self) -> usize {
+        self.world.fetch::<WaveDirector>().number()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.world.fetch::<SeededRng>().seed()
+    }
+
+    pub fn config(&self) -> Config {
+        (*self.world.fetch::<Config>()).clone()
+    }
+
+    /// Swaps in new tuning part way through a game. Systems read the config every tick so
+    /// most values take effect on the next one; the player's drag and the birds' sizes and
+    /// hit circles are copied onto them when they are created, so those are updated here. Survival
+    /// points already waiting on a longer interval are brought forward to the new one. The
+    /// wave underway keeps the birds it planned but spawns them at the new rate.
+    pub fn set_config(&mut self, config: Config) {
+        let duration_since_start = self.duration_since_start();
+        self.next_score_increase_time = self
+            .next_score_increase_time
+            .min(duration_since_start + config.survival_score_interval);
+
+        {
+            let players = self.world.read_storage::<Player>();
+            let mut drags = self.world.write_storage::<Drag>();
+            for (_player, drag) in (&players, &mut drags).join() {
+                drag.set(config.player_drag);
+            }
+
+            let bird_species = self.world.read_storage::<BirdSpecies>();
+            let mut colliders = self.world.write_storage::<Collider>();
+            let mut widths = self.world.write_storage::<Width>();
+            let mut heights = self.world.write_storage::<Height>();
+            for (bird_species, collider, width, height) in
+                (&bird_species, &mut colliders, &mut widths, &mut heights).join()
+            {
+                let size = config.species(bird_species.get()).size;
+                collider.shape = Shape::Circle {
+                    radius: config.bird_hit_distance * size,
+                };
+                *width = Width::new(config.bird_width * size);
+                *height = Height::new(config.bird_height * size);
+            }
+        }
+
+        self.world.insert(config);
+    }
+
+    /// Milliseconds of game time that have passed, counted in whole ticks so that every
+    /// machine agrees on it.
+    pub fn duration_since_start(&self) -> u128 {
+        self.ticks as u128 * 1000 / TICKS_PER_SECOND as u128
+    }
+
+    /// Advances the world by exactly one fixed step of `DELTA_TIME` seconds.
+    pub fn tick(&mut self, input: &Input) {
+        self.ticks += 1;
+        let duration_since_start = self.duration_since_start();
+        let config = self.config();
+        let mut gravity_system = GravitySystem;
+        let mut move_system = ApplyForceSystem {
+            delta_time: DELTA_TIME,
+        };
+        let mut hit_ground = HitGround;
+        let mut move_player_system = MovePlayerSystem { input };
+        let mut drag_system = DragSystem;
+        let mut keep_in_bounds = KeepInBoundsSystem;
+        let mut keep_birds_above_floor = KeepBirdsAboveFloorSystem;
+        let mut update_spatial_grid = UpdateSpatialGridSystem;
+        let mut detect_collisions = DetectCollisionsSystem;
+        let mut check_egg = CheckEggSystem {
+            duration_since_start,
+        };
+        let mut fly_system = FlySystem;
+        let mut follow_carrier = FollowCarrierSystem;
+        let mut reset_bullets = ResetBulletsSystem;
+        let mut hide_hit_bullets = HideHitBullets;
+        let mut recover_from_stun = RecoverFromStunSystem {
+            duration_since_start,
+        };
+        let mut drop_poop = DropPoopSystem {
+            duration_since_start,
+        };
+        let mut expire_effects = ExpireEffectsSystem {
+            duration_since_start,
+        };
+        let mut expire_lifetimes = ExpireLifetimesSystem {
+            duration_since_start,
+        };
+        let mut bullet_pool = BulletPoolSystem;
+        let mut flap_wings = FlapWingsSystem;
+        let mut animation = AnimationSystem {
+            delta_time: DELTA_TIME,
+        };
+        let mut update_explosions = UpdateExplosionsSystem {
+            duration_since_start,
+        };
+        recover_from_stun.run_now(&self.world);
+        expire_effects.run_now(&self.world);
+        expire_lifetimes.run_now(&self.world);
+        bullet_pool.run_now(&self.world);
+        if input.firing {
+            let mut fire_bullet_system = FireBulletSystem {
+                mouse_location: input.mouse_location,
+                duration_since_start,
+            };
+            fire_bullet_system.run_now(&self.world);
+            let mut launch_grenade_system = LaunchGrenadeSystem {
+                mouse_location: input.mouse_location,
+                duration_since_start,
+            };
+            launch_grenade_system.run_now(&self.world);
+        }
+
+        let wave_update = {
+            let birds_alive = self.world.read_storage::<Flyer>().join().count();
+            let mut seeded_rng = self.world.write_resource::<SeededRng>();
+            let mut wave_director = self.world.write_resource::<WaveDirector>();
+            wave_director.update(duration_since_start, birds_alive, &config, seeded_rng.rng())
+        };
+        if let Some(wave) = wave_update.cleared {
+            let bonus = config.wave_clear_bonus * wave;
+            self.world
+                .write_resource::<Score>()
+                .add(ScoreCategory::Waves, bonus);
+            let arena = self.arena();
+            build_popup(
+                self.world.create_entity(),
+                (arena.width() / 2.0, arena.height() / 3.0),
+                format!("Wave {} cleared +{}", wave, bonus),
+                graphics::WHITE,
+                duration_since_start,
+                &config,
+            );
+        }
+        for (species, edge) in wave_update.spawns {
+            self.create_bird(species, edge);
+        }
+
+        DecayComboSystem {
+            duration_since_start,
+        }
+        .run_now(&self.world);
+        if self.next_score_increase_time < duration_since_start {
+            let mut score_system = IncreaseScoreBySurvivingSystem;
+            score_system.run_now(&self.world);
+            self.next_score_increase_time = duration_since_start + config.survival_score_interval;
+        }
+
+        gravity_system.run_now(&self.world);
+        hit_ground.run_now(&self.world);
+        move_system.run_now(&self.world);
+        move_player_system.run_now(&self.world);
+        drag_system.run_now(&self.world);
+        keep_in_bounds.run_now(&self.world);
+        keep_birds_above_floor.run_now(&self.world);
+        update_explosions.run_now(&self.world);
+        update_spatial_grid.run_now(&self.world);
+        detect_collisions.run_now(&self.world);
+        fly_system.run_now(&self.world);
+        GrabEggSystem {
+            collisions: &mut self.grab_egg_collisions,
+        }
+        .run_now(&self.world);
+        follow_carrier.run_now(&self.world);
+        CatchEggSystem {
+            collisions: &mut self.catch_egg_collisions,
+        }
+        .run_now(&self.world);
+        check_egg.run_now(&self.world);
+        reset_bullets.run_now(&self.world);
+        DetonateGrenadesSystem {
+            duration_since_start,
+            collisions: &mut self.grenade_collisions,
+        }
+        .run_now(&self.world);
+        let mut killed = BitSet::new();
+        ShootBirdsSystem {
+            duration_since_start,
+            collisions: &mut self.shoot_birds_collisions,
+            killed: &mut killed,
+        }
+        .run_now(&self.world);
+        // holding the key down only sets off one bomb
+        if input.bomb && !self.bomb_held {
+            ClearScreenSystem {
+                duration_since_start,
+                killed: &mut killed,
+            }
+            .run_now(&self.world);
+        }
+        self.bomb_held = input.bomb;
+        hide_hit_bullets.run_now(&self.world);
+        drop_poop.run_now(&self.world);
+        HitByPoopSystem {
+            duration_since_start,
+            collisions: &mut self.poop_collisions,
+        }
+        .run_now(&self.world);
+        CollectPickupsSystem {
+            duration_since_start,
+            collisions: &mut self.pickup_collisions,
+        }
+        .run_now(&self.world);
+        flap_wings.run_now(&self.world);
+        animation.run_now(&self.world);
+
+        self.world.maintain();
+    }
+
+    /// Sends in one bird from just outside `edge`, somewhere along it at random.
+    fn create_bird(&mut self, species: Species, edge: SpawnEdge) {
+        let config = self.config();
+        let stats = config.species(species);
+        let width = config.bird_width * stats.size;
+        let height = config.bird_height * stats.size;
+        let arena = self.arena();
+        let position = {
+            let mut seeded_rng = self.world.write_resource::<SeededRng>();
+            let rng = seeded_rng.rng();
+            match edge {
+                SpawnEdge::Top => Position {
+                    x: rng.gen_range(-width, arena.width() + width),
+                    y: -height - 10.0,
+                },
+                SpawnEdge::Left => Position {
+                    x: -width - 10.0,
+                    y: rng.gen_range(0.0, arena.height() / 3.0),
+                },
+                SpawnEdge::Right => Position {
+                    x: arena.width() + width + 10.0,
+                    y: rng.gen_range(0.0, arena.height() / 3.0),
+                },
+            }
+        };
+
+        let mut builder = self
+            .world
+            .create_entity()
+            .with(position)
+            .with(ObjectMesh::new(MeshType::Bird(WingPose::Level)))
+            .with(Animation::new(
+                vec![
+                    MeshType::Bird(WingPose::Up),
+                    MeshType::Bird(WingPose::Level),
+                    MeshType::Bird(WingPose::Down),
+                    MeshType::Bird(WingPose::Level),
+                ],
+                0.0,
+            ))
+            .with(Height::new(height))
+            .with(Width::new(width))
+            .with(Collider::circle(
+                config.bird_hit_distance * stats.size,
+                Layers::BIRD,
+                Layers::NONE,
+            ))
+            .with(Velocity { x: 0.0, y: 0.0 })
+            .with(Acceleration { x: 0.0, y: 0.0 })
+            .with(Drag::new(0.0))
+            .with(Flyer)
+            .with(BirdSpecies::new(species))
+            .with(HitPoints::new(stats.hit_points));
+        if config.bird_wrap_around {
+            builder = builder.with(KeepInBounds::new(EdgeBehaviour::Wrap));
+        }
+        builder.build();
+    }
+}
@@ -0,0 +1,160 @@
+use specs::Entity;
+use std::collections::HashMap;
+
+/// About twice the size of the biggest bird, so a query near a bird only has to look at
+/// the cells right around it.
+pub const DEFAULT_CELL_SIZE: f32 = 64.0;
+
+/// Buckets entities by where they are so collision checks only look at what is nearby
+/// instead of everything in the world. Rebuilt from scratch every tick by
+/// `UpdateSpatialGridSystem`, so it is only accurate until something moves again.
+///
+/// Each entity goes in the one cell its centre is in and queries look far enough around
+/// to catch the biggest box, so nothing is ever found twice.
+#[derive(Debug)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<Entry>>,
+    largest_half_width: f32,
+    largest_half_height: f32,
+}
+
+/// An entity and the box it takes up.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    entity: Entity,
+    min_x: f32,
+    min_y: f32,
+    max_x: f32,
+    max_y: f32,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> SpatialGrid {
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+            largest_half_width: 0.0,
+            largest_half_height: 0.0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.largest_half_width = 0.0;
+        self.largest_half_height = 0.0;
+    }
+
+    /// Adds `entity` with a box reaching `half_width` either side of `x` and
+    /// `half_height` above and below `y`.
+    pub fn insert(&mut self, entity: Entity, x: f32, y: f32, half_width: f32, half_height: f32) {
+        let entry = Entry {
+            entity,
+            min_x: x - half_width,
+            min_y: y - half_height,
+            max_x: x + half_width,
+            max_y: y + half_height,
+        };
+        self.largest_half_width = self.largest_half_width.max(half_width);
+        self.largest_half_height = self.largest_half_height.max(half_height);
+        self.cells.entry(self.cell(x, y)).or_default().push(entry);
+    }
+
+    /// Every entity whose box comes within `distance` of the point on both axes. Callers
+    /// still need to check the real distance, this only rules out what is too far away to
+    /// matter. The order only depends on where things are and the order they were added,
+    /// so a replay always gets the same answer.
+    pub fn query(&self, x: f32, y: f32, distance: f32) -> Vec<Entity> {
+        let (min_x, min_y) = (x - distance, y - distance);
+        let (max_x, max_y) = (x + distance, y + distance);
+        let (min_cell_x, min_cell_y) = self.cell(
+            min_x - self.largest_half_width,
+            min_y - self.largest_half_height,
+        );
+        let (max_cell_x, max_cell_y) = self.cell(
+            max_x + self.largest_half_width,
+            max_y + self.largest_half_height,
+        );
+        let mut found = vec![];
+
+        for cell_x in min_cell_x..=max_cell_x {
+            for cell_y in min_cell_y..=max_cell_y {
+                if let Some(entries) = self.cells.get(&(cell_x, cell_y)) {
+                    found.extend(
+                        entries
+                            .iter()
+                            .filter(|entry| {
+                                entry.min_x <= max_x
+                                    && entry.max_x >= min_x
+                                    && entry.min_y <= max_y
+                                    && entry.max_y >= min_y
+                            })
+                            .map(|entry| entry.entity),
+                    );
+                }
+            }
+        }
+
+        found
+    }
+
+    fn cell(&self, x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / self.cell_size).floor() as i32,
+            (y / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+impl Default for SpatialGrid {
+    fn default() -> SpatialGrid {
+        SpatialGrid::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, World, WorldExt};
+
+    #[test]
+    fn finds_only_what_is_nearby() {
+        let mut world = World::new();
+        let near = world.create_entity().build();
+        let far = world.create_entity().build();
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert(near, 12.0, 12.0, 2.0, 2.0);
+        grid.insert(far, 100.0, 12.0, 2.0, 2.0);
+
+        assert_eq!(grid.query(5.0, 5.0, 5.0), vec![near]);
+        assert!(grid.query(50.0, 50.0, 5.0).is_empty());
+    }
+
+    #[test]
+    fn finds_big_boxes_from_cells_away() {
+        let mut world = World::new();
+        let wide = world.create_entity().build();
+        let mut grid = SpatialGrid::new(10.0);
+        grid.insert(wide, 0.0, 0.0, 45.0, 1.0);
+
+        assert_eq!(grid.query(44.0, 0.0, 0.0), vec![wide]);
+        assert!(grid.query(47.0, 0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn finds_each_entity_once() {
+        let mut world = World::new();
+        let entities: Vec<Entity> = (0..5).map(|_| world.create_entity().build()).collect();
+        let mut grid = SpatialGrid::new(10.0);
+        for (index, entity) in entities.iter().enumerate() {
+            grid.insert(*entity, index as f32 * 10.0, 0.0, 30.0, 30.0);
+        }
+
+        let mut found = grid.query(20.0, 0.0, 5.0);
+        found.sort();
+        assert_eq!(found, entities);
+
+        grid.clear();
+        assert!(grid.query(20.0, 0.0, 5.0).is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+use super::config::{milliseconds, Config};
+use ggez::graphics::Color;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The kinds of bird that come after the egg. Tougher and faster kinds only start turning
+/// up once the game has been going for a while.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Species {
+    Pigeon,
+    Hawk,
+    Goose,
+}
+
+/// Everything that makes one species fly and die differently from another. Each species
+/// has its own table in the config file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpeciesStats {
+    /// Red, green and blue from 0 to 1.
+    pub color: [f32; 3],
+    /// Multiplies the size of the bird mesh and its hit box.
+    pub size: f32,
+    /// Pixels per second squared.
+    pub acceleration: f32,
+    /// Pixels per second.
+    pub max_speed: f32,
+    pub hit_points: u32,
+    pub score: usize,
+    /// How likely this species is to be picked compared to the others.
+    pub spawn_weight: u32,
+    /// Milliseconds into the game before this species can spawn.
+    #[serde(with = "milliseconds")]
+    pub appears_after: u128,
+}
+
+impl SpeciesStats {
+    pub fn color(&self) -> Color {
+        Color::new(self.color[0], self.color[1], self.color[2], 1.0)
+    }
+}
+
+impl Species {
+    pub const ALL: [Species; 3] = [Species::Pigeon, Species::Hawk, Species::Goose];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Species::Pigeon => "pigeon",
+            Species::Hawk => "hawk",
+            Species::Goose => "goose",
+        }
+    }
+
+    /// Picks a species at random, weighted by `spawn_weight`, from the ones that are
+    /// allowed to appear by now.
+    pub fn choose(rng: &mut impl Rng, duration_since_start: u128, config: &Config) -> Species {
+        let available: Vec<(Species, u32)> = Species::ALL
+            .iter()
+            .map(|species| (*species, config.species(*species)))
+            .filter(|(_species, stats)| stats.appears_after <= duration_since_start)
+            .map(|(species, stats)| (species, stats.spawn_weight))
+            .collect();
+        let total_weight: u32 = available.iter().map(|(_species, weight)| weight).sum();
+
+        let mut roll = rng.gen_range(0, total_weight);
+        for (species, weight) in available {
+            if roll < weight {
+                return species;
+            }
+            roll -= weight;
+        }
+        Species::Pigeon
+    }
+}
@@ -1,35 +1,91 @@
 use super::components::{
-    Acceleration, Bullet, BulletState, CurrentBulletState, Flyer, HasGravity, Height, KeepAlive,
-    ObjectMesh, OnGround, Player, Position, Radius, Velocity, Width,
+    Acceleration, ActiveEffects, Animation, BirdSpecies, Bombs, Bullet, BulletState, CarriedBy,
+    CarryingEgg, Collider, CurrentBulletState, DelayPoopUntilAfter, Drag, EdgeBehaviour, Explosion,
+    Falling, FloatingText, Flyer, Grenade, HasGravity, Height, HitPoints, KeepAlive, KeepInBounds,
+    Layers, Lifetime, MeshType, ObjectMesh, OnGround, Pickup, Player, Poop, Position, PowerUp,
+    Shape, StolenBy, StunnedUntil, TemporaryBullet, Velocity, Width, WingPose,
 };
-use super::meshes;
-use super::resources::{BulletSize, DelayFiringUntilAfter, Score, StillAlive};
-use ggez::event::KeyCode;
-use ggez::input::keyboard;
+use super::config::Config;
+use super::meshes::Meshes;
+use super::resources::{
+    ArenaBounds, BulletPoolSize, Collision, DelayFiringUntilAfter, GameOver, GameOverReason,
+    RunStats, SeededRng, StillAlive,
+};
+use super::scoring::{Kill, Score, ScoreCategory};
+use super::simulation::{Input, GRENADE_SIZE, POOP_SIZE, POWER_UP_SIZE};
+use super::spatial_grid::SpatialGrid;
+use super::waves::WaveDirector;
 use ggez::nalgebra::{Point2, Vector2};
 use ggez::{graphics, Context};
+use rand::seq::SliceRandom;
+use rand::Rng;
 use specs::prelude::*;
+use specs::shrev::{EventChannel, ReaderId};
 use specs::Entities;
-use std::collections::HashSet;
-use std::time::Duration;
 
-pub struct GravitySystem {
-    pub arena_height: f32,
+// how far past the edge a bird carrying the egg aims for, so the egg clears the screen
+const ESCAPE_MARGIN: f32 = 100.0;
+const EGG_CARRY_OFFSET: f32 = 15.0;
+const EGG_REST_HEIGHT: f32 = 25.0;
+const STUNNED_COLOR: graphics::Color = graphics::Color::new(1.0, 1.0, 0.0, 1.0);
+const POINTS_COLOR: graphics::Color = graphics::Color::new(1.0, 1.0, 1.0, 1.0);
+const COMBO_COLOR: graphics::Color = graphics::Color::new(1.0, 0.5, 0.0, 1.0);
+const PICKUP_COLOR: graphics::Color = graphics::Color::new(0.4, 1.0, 0.4, 1.0);
+// how far above a popup the next one about the same thing goes, so they don't overlap
+const POPUP_SPACING: f32 = 20.0;
+// how far above the player's position the stunned popup starts, clear of its head
+const STUNNED_POPUP_HEIGHT: f32 = 70.0;
+const EXPLOSION_DURATION: u128 = 300;
+// wing beats speed up with the bird, in frames per second plus frames per pixel per second
+const FLAP_RATE: f32 = 3.0;
+const FLAP_RATE_PER_SPEED: f32 = 0.15;
+const DIVE_SPEED: f32 = 40.0;
+const HOVER_DISTANCE: f32 = 50.0;
+const HOVER_SPEED: f32 = 5.0;
+// birds stop steering this close to the egg instead of jittering back and forth across it
+const STEERING_DEAD_ZONE: f32 = 25.0;
+
+/// Text that floats up from `(x, y)` and fades out, like the points for a kill. Takes any
+/// builder so it works from inside a system through `LazyUpdate` as well as on the world.
+pub fn build_popup<B: Builder>(
+    builder: B,
+    (x, y): (f32, f32),
+    text: String,
+    color: graphics::Color,
+    duration_since_start: u128,
+    config: &Config,
+) -> Entity {
+    builder
+        // birds shot just above the screen still show their points
+        .with(Position {
+            x,
+            y: y.max(POPUP_SPACING),
+        })
+        .with(Velocity {
+            x: 0.0,
+            y: -config.popup_rise_speed,
+        })
+        .with(FloatingText { text, color })
+        .with(Lifetime::new(duration_since_start, config.popup_duration))
+        .build()
 }
 
+pub struct GravitySystem;
+
 impl<'a> System<'a> for GravitySystem {
     type SystemData = (
         WriteStorage<'a, Acceleration>,
         ReadStorage<'a, HasGravity>,
         ReadStorage<'a, OnGround>,
+        Read<'a, Config>,
     );
 
-    fn run(&mut self, (mut acceleration, has_gravity, on_ground): Self::SystemData) {
+    fn run(&mut self, (mut acceleration, has_gravity, on_ground, config): Self::SystemData) {
         for (acceleration, _has_gravity, on_ground) in
             (&mut acceleration, &has_gravity, &on_ground).join()
         {
             if !on_ground.get() {
-                acceleration.y += 0.5;
+                acceleration.y += config.gravity;
             }
         }
     }
@@ -52,14 +108,12 @@ impl<'a> System<'a> for ApplyForceSystem {
         (mut acceleration, mut position, mut velocity, still_alive): Self::SystemData,
     ) {
         if still_alive.get() {
-            let horizontal_limit = 10.0;
-            let vertical_limit = 0.6;
             for (acceleration, position, velocity) in
                 ((&mut acceleration).maybe(), &mut position, &mut velocity).join()
             {
                 if let Some(acceleration) = acceleration {
-                    velocity.x += acceleration.x;
-                    velocity.y += acceleration.y;
+                    velocity.x += acceleration.x * self.delta_time;
+                    velocity.y += acceleration.y * self.delta_time;
                     acceleration.x = 0.0;
                     acceleration.y = 0.0;
                 }
@@ -70,13 +124,105 @@ impl<'a> System<'a> for ApplyForceSystem {
     }
 }
 
+/// Puts everything with a collider into the spatial grid. Runs once things have moved for
+/// the tick, before anything checks what it is touching.
+pub struct UpdateSpatialGridSystem;
+
+impl<'a> System<'a> for UpdateSpatialGridSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Collider>,
+        Write<'a, SpatialGrid>,
+    );
+
+    fn run(&mut self, (entities, position, collider, mut spatial_grid): Self::SystemData) {
+        spatial_grid.clear();
+
+        for (entity, position, collider) in (&entities, &position, &collider).join() {
+            let (half_width, half_height) = collider.shape.half_extents();
+            spatial_grid.insert(entity, position.x, position.y, half_width, half_height);
+        }
+    }
+}
+
+/// The one place that decides what is touching what. Every collider with a mask looks
+/// around itself in the spatial grid and sends a `Collision` for each collider it overlaps
+/// on one of those layers.
+pub struct DetectCollisionsSystem;
+
+impl<'a> System<'a> for DetectCollisionsSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Collider>,
+        Read<'a, SpatialGrid>,
+        Write<'a, EventChannel<Collision>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, position, collider, spatial_grid, mut collisions): Self::SystemData,
+    ) {
+        for (entity, entity_position, entity_collider) in (&entities, &position, &collider).join() {
+            if entity_collider.mask == Layers::NONE {
+                continue;
+            }
+
+            let (half_width, half_height) = entity_collider.shape.half_extents();
+            let reach = half_width.max(half_height);
+            for other in spatial_grid.query(entity_position.x, entity_position.y, reach) {
+                if other == entity {
+                    continue;
+                }
+                let (other_position, other_collider) =
+                    match (position.get(other), collider.get(other)) {
+                        (Some(other_position), Some(other_collider)) => {
+                            (other_position, other_collider)
+                        }
+                        _ => continue,
+                    };
+
+                if entity_collider.mask.intersects(other_collider.layer)
+                    && entity_collider.shape.overlaps(
+                        entity_position,
+                        &other_collider.shape,
+                        other_position,
+                    )
+                {
+                    collisions.single_write(Collision { entity, other });
+                }
+            }
+        }
+    }
+}
+
 pub struct RenderSystem<'a> {
     pub context: &'a mut Context,
+    pub meshes: &'a Meshes,
+    pub duration_since_start: u128,
 }
 
 impl RenderSystem<'_> {
-    fn draw_score_small(&mut self, score: usize) {
-        let text = graphics::Text::new(format!("Score: {}", score));
+    fn draw_score_small(&mut self, score: &Score, config: &Config, wave_director: &WaveDirector) {
+        let wave_text = match wave_director.next_wave_at() {
+            Some(next_wave_at) if wave_director.number() > 0 => format!(
+                "Wave {} cleared, next in {:.1}s",
+                wave_director.number(),
+                next_wave_at.saturating_sub(self.duration_since_start) as f32 / 1000.0
+            ),
+            _ => format!("Wave: {}", wave_director.number()),
+        };
+        let combo_text = match score.multiplier(config) {
+            1 => String::new(),
+            multiplier => format!("    x{} combo", multiplier),
+        };
+        let text = graphics::Text::new(format!(
+            "Score: {}{}    {}",
+            score.get(),
+            combo_text,
+            wave_text
+        ));
 
         graphics::draw(
             self.context,
@@ -86,20 +232,18 @@ impl RenderSystem<'_> {
         .unwrap();
     }
 
-    fn draw_score_large(&mut self, score: usize, arena_width: f32, arena_height: f32) {
-        let mut text = graphics::Text::new(format!("You Scored {}", score));
-        let font = graphics::Font::default();
-        let font_scale = graphics::Scale::uniform(100.0);
+    fn draw_active_effects(&mut self, active_effects: &ActiveEffects, now: u128) {
+        for (index, (power_up, ends_at)) in active_effects.iter().enumerate() {
+            let seconds_left = ends_at.saturating_sub(now) as f32 / 1000.0;
+            let text = graphics::Text::new(format!("{}: {:.1}s", power_up.name(), seconds_left));
 
-        text.set_font(font, font_scale);
-
-        graphics::draw(
-            self.context,
-            &text,
-            graphics::DrawParam::default()
-                .dest(Point2::new(arena_width / 5.0, arena_height / 2.0 + 50.0)),
-        )
-        .unwrap();
+            graphics::draw(
+                self.context,
+                &text,
+                graphics::DrawParam::default().dest(Point2::new(5.0, 45.0 + index as f32 * 20.0)),
+            )
+            .unwrap();
+        }
     }
 }
 
@@ -107,12 +251,37 @@ impl<'a> System<'a> for RenderSystem<'a> {
     type SystemData = (
         ReadStorage<'a, Position>,
         ReadStorage<'a, ObjectMesh>,
-        Read<'a, StillAlive>,
         ReadStorage<'a, BulletState>,
         Read<'a, Score>,
+        ReadStorage<'a, StunnedUntil>,
+        ReadStorage<'a, ActiveEffects>,
+        ReadStorage<'a, Explosion>,
+        ReadStorage<'a, Bombs>,
+        ReadStorage<'a, BirdSpecies>,
+        ReadStorage<'a, FloatingText>,
+        ReadStorage<'a, Lifetime>,
+        Read<'a, Config>,
+        Read<'a, WaveDirector>,
     );
 
-    fn run(&mut self, (position, mesh, still_alive, bullet_state, score): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            position,
+            mesh,
+            bullet_state,
+            score,
+            stunned_until,
+            active_effects,
+            explosion,
+            bombs,
+            bird_species,
+            floating_text,
+            lifetime,
+            config,
+            wave_director,
+        ): Self::SystemData,
+    ) {
         let mut bullet_count_text = String::from("Bullets: ");
         for bullet_state in bullet_state.join() {
             if let CurrentBulletState::Ready = bullet_state.get() {
@@ -120,30 +289,52 @@ impl<'a> System<'a> for RenderSystem<'a> {
             }
         }
 
-        for (position, mesh) in (&position, &mesh).join() {
-            graphics::draw(
-                self.context,
-                mesh.get(),
-                graphics::DrawParam::default().dest(Point2::new(position.x, position.y)),
-            )
-            .unwrap();
+        for (position, mesh, stunned_until, explosion, bird_species) in (
+            &position,
+            &mesh,
+            stunned_until.maybe(),
+            explosion.maybe(),
+            bird_species.maybe(),
+        )
+            .join()
+        {
+            let mut draw_param =
+                graphics::DrawParam::default().dest(Point2::new(position.x, position.y));
+            if stunned_until.is_some() {
+                draw_param = draw_param.color(STUNNED_COLOR);
+            }
+            if let Some(explosion) = explosion {
+                draw_param = draw_param.scale(Vector2::new(explosion.radius, explosion.radius));
+            }
+            if let Some(bird_species) = bird_species {
+                let stats = config.species(bird_species.get());
+                draw_param = draw_param
+                    .color(stats.color())
+                    .scale(Vector2::new(stats.size, stats.size));
+            }
+            graphics::draw(self.context, self.meshes.get(mesh.get()), draw_param).unwrap();
         }
-        if !still_alive.get() {
-            let (arena_width, arena_height) = graphics::drawable_size(self.context);
-            let font = graphics::Font::default();
-            let font_scale = graphics::Scale::uniform(100.0);
-            let mut game_over_text = graphics::Text::new("Game Over");
-            game_over_text.set_font(font, font_scale);
+        for (position, floating_text, lifetime) in (&position, &floating_text, &lifetime).join() {
+            let text = graphics::Text::new(floating_text.text.as_str());
+            let width = text.width(self.context) as f32;
+            let mut color = floating_text.color;
+            color.a = 1.0 - lifetime.progress(self.duration_since_start);
+
             graphics::draw(
                 self.context,
-                &game_over_text,
+                &text,
                 graphics::DrawParam::default()
-                    .dest(Point2::new(arena_width / 4.0, arena_height / 2.0 - 100.0)),
+                    .dest(Point2::new(position.x - width / 2.0, position.y))
+                    .color(color),
             )
             .unwrap();
-            self.draw_score_large(score.get(), arena_width, arena_height);
-        } else {
-            self.draw_score_small(score.get());
+        }
+        for bombs in bombs.join() {
+            bullet_count_text = format!("{}    Bombs: {}", bullet_count_text, bombs.get());
+        }
+        self.draw_score_small(&score, &config, &wave_director);
+        for active_effects in active_effects.join() {
+            self.draw_active_effects(active_effects, self.duration_since_start);
         }
 
         graphics::draw(
@@ -155,9 +346,7 @@ impl<'a> System<'a> for RenderSystem<'a> {
     }
 }
 
-pub struct HitGround {
-    pub arena_height: f32,
-}
+pub struct HitGround;
 
 impl<'a> System<'a> for HitGround {
     type SystemData = (
@@ -165,15 +354,33 @@ impl<'a> System<'a> for HitGround {
         ReadStorage<'a, Height>,
         ReadStorage<'a, HasGravity>,
         WriteStorage<'a, OnGround>,
+        WriteStorage<'a, Velocity>,
+        Read<'a, ArenaBounds>,
     );
 
-    fn run(&mut self, (mut position, height, has_gravity, mut on_ground): Self::SystemData) {
-        for (position, height, _has_gravity, on_ground) in
-            (&mut position, &height, &has_gravity, &mut on_ground).join()
+    fn run(
+        &mut self,
+        (mut position, height, has_gravity, mut on_ground, mut velocity, arena): Self::SystemData,
+    ) {
+        for (position, height, _has_gravity, on_ground, velocity) in (
+            &mut position,
+            &height,
+            &has_gravity,
+            &mut on_ground,
+            (&mut velocity).maybe(),
+        )
+            .join()
         {
-            if position.y + height.get() > self.arena_height {
-                position.y = self.arena_height - height.get();
+            if position.y + height.get() >= arena.height() {
+                position.y = arena.height() - height.get();
                 on_ground.set(true);
+                // landing has to stop the fall, otherwise the next jump is fighting all of
+                // the speed picked up on the way down
+                if let Some(velocity) = velocity {
+                    if velocity.y > 0.0 {
+                        velocity.y = 0.0;
+                    }
+                }
             } else {
                 on_ground.set(false);
             }
@@ -181,33 +388,125 @@ impl<'a> System<'a> for HitGround {
     }
 }
 
+/// Holds everything with `KeepInBounds` to the left and right edges of the arena. The top
+/// is left open for birds to come and go, and `HitGround` already handles the floor.
+pub struct KeepInBoundsSystem;
+
+impl<'a> System<'a> for KeepInBoundsSystem {
+    type SystemData = (
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, Width>,
+        ReadStorage<'a, KeepInBounds>,
+        Read<'a, ArenaBounds>,
+    );
+
+    fn run(
+        &mut self,
+        (mut position, mut velocity, width, keep_in_bounds, arena): Self::SystemData,
+    ) {
+        for (position, velocity, width, keep_in_bounds) in
+            (&mut position, &mut velocity, width.maybe(), &keep_in_bounds).join()
+        {
+            let half_width = width.map_or(0.0, Width::get);
+            let left = half_width;
+            let right = arena.width() - half_width;
+
+            match keep_in_bounds.get() {
+                EdgeBehaviour::Clamp => {
+                    if position.x < left {
+                        position.x = left;
+                        velocity.x = velocity.x.max(0.0);
+                    } else if position.x > right {
+                        position.x = right;
+                        velocity.x = velocity.x.min(0.0);
+                    }
+                }
+                EdgeBehaviour::Bounce => {
+                    if position.x < left && velocity.x < 0.0 {
+                        position.x = left;
+                        velocity.x = -velocity.x;
+                    } else if position.x > right && velocity.x > 0.0 {
+                        position.x = right;
+                        velocity.x = -velocity.x;
+                    }
+                }
+                // only once it is heading further out, so birds flying in from the side
+                // aren't thrown straight back out of the other one
+                EdgeBehaviour::Wrap => {
+                    if position.x < -half_width && velocity.x < 0.0 {
+                        position.x = arena.width() + half_width;
+                    } else if position.x > arena.width() + half_width && velocity.x > 0.0 {
+                        position.x = -half_width;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Turns birds back up when they reach the floor. Steering at the egg overshoots, and
+/// without this a bird could swing out under the arena where it can't be shot, holding up
+/// the end of its wave.
+pub struct KeepBirdsAboveFloorSystem;
+
+impl<'a> System<'a> for KeepBirdsAboveFloorSystem {
+    type SystemData = (
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, Height>,
+        ReadStorage<'a, Flyer>,
+        Read<'a, ArenaBounds>,
+    );
+
+    fn run(&mut self, (mut position, mut velocity, height, flyer, arena): Self::SystemData) {
+        for (position, velocity, height, _flyer) in
+            (&mut position, &mut velocity, &height, &flyer).join()
+        {
+            let lowest = arena.height() - height.get();
+            if position.y > lowest {
+                position.y = lowest;
+                velocity.y = -velocity.y.abs();
+            }
+        }
+    }
+}
+
 pub struct MovePlayerSystem<'a> {
-    pub pressed_keys: &'a HashSet<KeyCode>,
+    pub input: &'a Input,
 }
 
 impl<'a> System<'a> for MovePlayerSystem<'a> {
     type SystemData = (
-        WriteStorage<'a, Position>,
         WriteStorage<'a, Acceleration>,
+        WriteStorage<'a, Velocity>,
         ReadStorage<'a, OnGround>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, StunnedUntil>,
+        Read<'a, Config>,
     );
 
-    fn run(&mut self, (mut position, mut acceleration, on_ground): Self::SystemData) {
-        let horizontal_speed = 0.8;
-        for (position, acceleration, on_ground) in
-            (&mut position, &mut acceleration, &on_ground).join()
+    fn run(
+        &mut self,
+        (mut acceleration, mut velocity, on_ground, player, stunned_until, config): Self::SystemData,
+    ) {
+        for (acceleration, velocity, on_ground, _player, ()) in (
+            &mut acceleration,
+            &mut velocity,
+            &on_ground,
+            &player,
+            !&stunned_until,
+        )
+            .join()
         {
-            if self.pressed_keys.contains(&KeyCode::A) || self.pressed_keys.contains(&KeyCode::Left)
-            {
-                acceleration.x -= horizontal_speed;
-            } else if self.pressed_keys.contains(&KeyCode::D)
-                || self.pressed_keys.contains(&KeyCode::Right)
-            {
-                acceleration.x += horizontal_speed;
+            if self.input.move_left {
+                acceleration.x -= config.player_acceleration;
+            } else if self.input.move_right {
+                acceleration.x += config.player_acceleration;
             }
 
-            if on_ground.get() && self.pressed_keys.contains(&KeyCode::Space) {
-                acceleration.y -= 50.5;
+            if on_ground.get() && self.input.jump {
+                velocity.y = -config.jump_speed;
             }
         }
     }
@@ -217,25 +516,23 @@ pub struct DragSystem;
 
 impl<'a> System<'a> for DragSystem {
     type SystemData = (
-        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, Velocity>,
         WriteStorage<'a, Acceleration>,
-        ReadStorage<'a, OnGround>,
+        ReadStorage<'a, Drag>,
     );
 
-    fn run(&mut self, (mut velocity, mut acceleration, on_ground): Self::SystemData) {
-        for (velocity, acceleration, on_ground) in
-            (&mut velocity, &mut acceleration, &on_ground).join()
-        {
-            let mut force = Vector2::new(velocity.x, velocity.y);
-            force = force.normalize();
-            force *= -1.0;
-
-            acceleration.x += force.x * 0.8;
+    fn run(&mut self, (velocity, mut acceleration, drag): Self::SystemData) {
+        for (velocity, acceleration, drag) in (&velocity, &mut acceleration, &drag).join() {
+            // proportional to speed so it slows the player down without ever pushing them
+            // back the other way
+            acceleration.x -= velocity.x * drag.get();
         }
     }
 }
 
-pub struct CheckEggSystem;
+pub struct CheckEggSystem {
+    pub duration_since_start: u128,
+}
 
 impl<'a> System<'a> for CheckEggSystem {
     type SystemData = (
@@ -243,95 +540,338 @@ impl<'a> System<'a> for CheckEggSystem {
         ReadStorage<'a, Height>,
         ReadStorage<'a, Width>,
         WriteStorage<'a, KeepAlive>,
+        ReadStorage<'a, Falling>,
+        ReadStorage<'a, OnGround>,
+        ReadStorage<'a, StolenBy>,
         Write<'a, StillAlive>,
+        Read<'a, ArenaBounds>,
     );
 
     fn run(
         &mut self,
-        (position, height, width, mut keep_alive, mut still_alive): Self::SystemData,
+        (
+            position,
+            height,
+            width,
+            mut keep_alive,
+            falling,
+            on_ground,
+            stolen_by,
+            mut still_alive,
+            arena,
+        ): Self::SystemData,
     ) {
-        for (entity_position, entity_height, entity_width, ()) in
-            (&position, &height, &width, !&keep_alive).join()
+        for (egg_position, egg_height, egg_width, keep_alive, falling, on_ground, stolen_by) in (
+            &position,
+            &height,
+            &width,
+            &mut keep_alive,
+            falling.maybe(),
+            on_ground.maybe(),
+            stolen_by.maybe(),
+        )
+            .join()
         {
-            for (egg_position, egg_height, egg_width, keep_alive) in
-                (&position, &height, &width, &keep_alive).join()
-            {
-                // should only be the egg
-                let entity_location = Vector2::new(entity_position.x, entity_position.y);
-                let egg_location = Vector2::new(egg_position.x, egg_position.y);
-                let distance = entity_location - egg_location;
-                let distance = distance.magnitude();
-
-                if distance < egg_width.get() {
-                    still_alive.set(false);
+            let mut reason = None;
+
+            // nobody caught it
+            if let (Some(_falling), Some(on_ground)) = (falling, on_ground) {
+                if on_ground.get() {
+                    reason = Some(GameOverReason::EggBroken);
                 }
             }
+
+            // the floor is at the bottom so the egg can only be taken out the sides or top
+            if egg_position.x < -egg_width.get()
+                || egg_position.x > arena.width() + egg_width.get()
+                || egg_position.y < -egg_height.get()
+            {
+                reason = Some(GameOverReason::EggStolen);
+            }
+
+            if let Some(reason) = reason {
+                keep_alive.die();
+                still_alive.end(GameOver {
+                    reason,
+                    species: stolen_by.map(StolenBy::get),
+                    at: self.duration_since_start,
+                });
+            }
         }
     }
 }
 
-pub struct FlySystem;
+pub struct GrabEggSystem<'a> {
+    pub collisions: &'a mut ReaderId<Collision>,
+}
 
-impl<'a> System<'a> for FlySystem {
+impl<'a> System<'a> for GrabEggSystem<'a> {
     type SystemData = (
         ReadStorage<'a, Position>,
         ReadStorage<'a, KeepAlive>,
-        WriteStorage<'a, Acceleration>,
         ReadStorage<'a, Flyer>,
+        WriteStorage<'a, CarriedBy>,
+        WriteStorage<'a, CarryingEgg>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, Falling>,
+        ReadStorage<'a, BirdSpecies>,
+        WriteStorage<'a, StolenBy>,
+        Read<'a, EventChannel<Collision>>,
+        Read<'a, ArenaBounds>,
     );
 
-    fn run(&mut self, (position, keep_alive, mut acceleration, flyer): Self::SystemData) {
-        for (flyer_position, flyer_acceleration, _flyer) in
-            (&position, &mut acceleration, &flyer).join()
-        {
-            let flyer_location = Vector2::new(flyer_position.x, flyer_position.y);
+    fn run(
+        &mut self,
+        (
+            position,
+            keep_alive,
+            flyer,
+            mut carried_by,
+            mut carrying_egg,
+            mut velocity,
+            falling,
+            bird_species,
+            mut stolen_by,
+            collisions,
+            arena,
+        ): Self::SystemData,
+    ) {
+        // the first bird to touch an egg that is lying on the ground takes it
+        for collision in collisions.read(self.collisions) {
+            let (egg, bird) = (collision.entity, collision.other);
+            if !keep_alive.contains(egg)
+                || !flyer.contains(bird)
+                || carried_by.contains(egg)
+                || falling.contains(egg)
+            {
+                continue;
+            }
+            let bird_position = match position.get(bird) {
+                Some(bird_position) => bird_position,
+                None => continue,
+            };
 
-            for (egg_position, _keep_alive) in (&position, &keep_alive).join() {
-                let egg_location = Vector2::new(egg_position.x, egg_position.y);
-                let mut direction = egg_location - flyer_location;
-                let distance = direction.magnitude();
+            // head for whichever of the left, right or top edge is closest
+            let to_left = bird_position.x;
+            let to_right = arena.width() - bird_position.x;
+            let to_top = bird_position.y;
+            let (escape_x, escape_y) = if to_top < to_left && to_top < to_right {
+                (bird_position.x, -ESCAPE_MARGIN)
+            } else if to_left < to_right {
+                (-ESCAPE_MARGIN, bird_position.y - ESCAPE_MARGIN)
+            } else {
+                (
+                    arena.width() + ESCAPE_MARGIN,
+                    bird_position.y - ESCAPE_MARGIN,
+                )
+            };
 
-                if distance > 25.0 {
-                    direction = direction.normalize();
-                    let force = direction * 0.05;
-                    flyer_acceleration.x += force.x;
-                    flyer_acceleration.y += force.y;
+            // latching on stops the dive, otherwise the bird carries the egg through the
+            // floor
+            if let Some(bird_velocity) = velocity.get_mut(bird) {
+                bird_velocity.x = 0.0;
+                bird_velocity.y = 0.0;
+            }
+
+            carried_by.insert(egg, CarriedBy::new(bird)).unwrap();
+            if let Some(bird_species) = bird_species.get(bird) {
+                stolen_by
+                    .insert(egg, StolenBy::new(bird_species.get()))
+                    .unwrap();
+            }
+            carrying_egg
+                .insert(bird, CarryingEgg { escape_x, escape_y })
+                .unwrap();
+        }
+    }
+}
+
+pub struct FollowCarrierSystem;
+
+impl<'a> System<'a> for FollowCarrierSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        WriteStorage<'a, CarriedBy>,
+        WriteStorage<'a, HasGravity>,
+        WriteStorage<'a, OnGround>,
+        WriteStorage<'a, Falling>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut position,
+            mut velocity,
+            mut carried_by,
+            mut has_gravity,
+            mut on_ground,
+            mut falling,
+        ): Self::SystemData,
+    ) {
+        let carried: Vec<(Entity, Entity)> = (&entities, &carried_by)
+            .join()
+            .map(|(egg, carried_by)| (egg, carried_by.get()))
+            .collect();
+
+        for (egg, carrier) in carried {
+            let carrier_state = match (position.get(carrier), velocity.get(carrier)) {
+                (Some(carrier_position), Some(carrier_velocity)) => Some((
+                    carrier_position.x,
+                    carrier_position.y,
+                    carrier_velocity.x,
+                    carrier_velocity.y,
+                )),
+                _ => None,
+            };
+
+            match carrier_state {
+                Some((x, y, velocity_x, velocity_y)) => {
+                    if let Some(egg_position) = position.get_mut(egg) {
+                        // hang the egg from the bird's feet
+                        egg_position.x = x;
+                        egg_position.y = y + EGG_CARRY_OFFSET;
+                    }
+                    // the egg keeps the carrier's velocity so it flies on when dropped
+                    if let Some(egg_velocity) = velocity.get_mut(egg) {
+                        egg_velocity.x = velocity_x;
+                        egg_velocity.y = velocity_y;
+                    }
+                }
+                None => {
+                    // the carrier was shot, so the egg drops
+                    carried_by.remove(egg);
+                    has_gravity.insert(egg, HasGravity).unwrap();
+                    on_ground.insert(egg, OnGround::new()).unwrap();
+                    falling.insert(egg, Falling).unwrap();
                 }
             }
         }
     }
 }
 
-pub struct LandOnEggSystem;
+pub struct CatchEggSystem<'a> {
+    pub collisions: &'a mut ReaderId<Collision>,
+}
 
-impl<'a> System<'a> for LandOnEggSystem {
+impl<'a> System<'a> for CatchEggSystem<'a> {
+    type SystemData = (
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        WriteStorage<'a, HasGravity>,
+        WriteStorage<'a, OnGround>,
+        WriteStorage<'a, Falling>,
+        ReadStorage<'a, Player>,
+        Read<'a, EventChannel<Collision>>,
+        Read<'a, ArenaBounds>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut position,
+            mut velocity,
+            mut has_gravity,
+            mut on_ground,
+            mut falling,
+            player,
+            collisions,
+            arena,
+        ): Self::SystemData,
+    ) {
+        // eggs hear about birds too, only the player can catch one
+        for collision in collisions.read(self.collisions) {
+            let egg = collision.entity;
+            if !player.contains(collision.other) || falling.remove(egg).is_none() {
+                continue;
+            }
+
+            // the player sets the egg back down where they caught it
+            has_gravity.remove(egg);
+            on_ground.remove(egg);
+            if let Some(egg_position) = position.get_mut(egg) {
+                egg_position.y = arena.height() - EGG_REST_HEIGHT;
+            }
+            if let Some(egg_velocity) = velocity.get_mut(egg) {
+                egg_velocity.x = 0.0;
+                egg_velocity.y = 0.0;
+            }
+        }
+    }
+}
+
+pub struct FlySystem;
+
+impl<'a> System<'a> for FlySystem {
     type SystemData = (
         ReadStorage<'a, Position>,
-        ReadStorage<'a, Flyer>,
         ReadStorage<'a, KeepAlive>,
         WriteStorage<'a, Acceleration>,
+        ReadStorage<'a, Flyer>,
+        ReadStorage<'a, CarryingEgg>,
+        ReadStorage<'a, BirdSpecies>,
         WriteStorage<'a, Velocity>,
+        Read<'a, Config>,
     );
 
     fn run(
         &mut self,
-        (position, flyer, keep_alive, mut acceleration, mut velocity): Self::SystemData,
+        (
+            position,
+            keep_alive,
+            mut acceleration,
+            flyer,
+            carrying_egg,
+            bird_species,
+            mut velocity,
+            config,
+        ): Self::SystemData,
     ) {
-        for (flyer_position, flyer, flyer_acceleration, flyer_velocity) in
-            (&position, &flyer, &mut acceleration, &mut velocity).join()
+        // every bird heads for the egg wherever it is, so there's no neighbourhood to narrow
+        // down, but there's no need to look the egg up again for every bird either
+        let egg_locations: Vec<Vector2<f32>> = (&position, &keep_alive)
+            .join()
+            .map(|(egg_position, _keep_alive)| Vector2::new(egg_position.x, egg_position.y))
+            .collect();
+
+        for (flyer_position, flyer_acceleration, _flyer, carrying_egg, bird_species, velocity) in (
+            &position,
+            &mut acceleration,
+            &flyer,
+            carrying_egg.maybe(),
+            &bird_species,
+            &mut velocity,
+        )
+            .join()
         {
             let flyer_location = Vector2::new(flyer_position.x, flyer_position.y);
+            let stats = config.species(bird_species.get());
+
+            let speed = Vector2::new(velocity.x, velocity.y).magnitude();
+            if speed > stats.max_speed {
+                velocity.x *= stats.max_speed / speed;
+                velocity.y *= stats.max_speed / speed;
+            }
+
+            if let Some(carrying_egg) = carrying_egg {
+                let escape_location = Vector2::new(carrying_egg.escape_x, carrying_egg.escape_y);
+                let force = (escape_location - flyer_location).normalize() * stats.acceleration;
+                flyer_acceleration.x += force.x;
+                flyer_acceleration.y += force.y;
+                continue;
+            }
 
-            for (egg_position, keep_alive) in (&position, &keep_alive).join() {
-                let egg_location = Vector2::new(egg_position.x, egg_position.y);
-                let direction = egg_location - flyer_location;
+            for egg_location in egg_locations.iter() {
+                let mut direction = egg_location - flyer_location;
                 let distance = direction.magnitude();
 
-                if distance < 2.0 {
-                    flyer_acceleration.x = 0.0;
-                    flyer_acceleration.y = 0.0;
-                    flyer_velocity.x = 0.0;
-                    flyer_velocity.y = 0.0;
+                if distance > STEERING_DEAD_ZONE {
+                    direction = direction.normalize();
+                    let force = direction * stats.acceleration;
+                    flyer_acceleration.x += force.x;
+                    flyer_acceleration.y += force.y;
                 }
             }
         }
@@ -348,10 +888,13 @@ impl<'a> System<'a> for FireBulletSystem {
         WriteStorage<'a, Position>,
         ReadStorage<'a, Player>,
         WriteStorage<'a, Velocity>,
-        Read<'a, BulletSize>,
         ReadStorage<'a, Bullet>,
         WriteStorage<'a, BulletState>,
         Write<'a, DelayFiringUntilAfter>,
+        ReadStorage<'a, StunnedUntil>,
+        ReadStorage<'a, ActiveEffects>,
+        Write<'a, RunStats>,
+        Read<'a, Config>,
     );
 
     fn run(
@@ -360,42 +903,65 @@ impl<'a> System<'a> for FireBulletSystem {
             mut position,
             player,
             mut velocity,
-            bullet_size,
             bullet,
             mut bullet_state,
             mut delay_firing_until_after,
+            stunned_until,
+            active_effects,
+            mut run_stats,
+            config,
         ): Self::SystemData,
     ) {
+        if (&player, &stunned_until).join().next().is_some() {
+            return;
+        }
+        // the grenade launcher takes over the trigger while it lasts
+        if (&player, &active_effects)
+            .join()
+            .any(|(_player, active_effects)| active_effects.is_active(PowerUp::GrenadeLauncher))
+        {
+            return;
+        }
+        // the machine gun still only lets one bullet out per tick
+        let cooldown = if (&player, &active_effects)
+            .join()
+            .any(|(_player, active_effects)| active_effects.is_active(PowerUp::MachineGun))
+        {
+            0
+        } else {
+            config.fire_cooldown
+        };
         let mut player_location = Vector2::new(-50.0, -50.0);
         let mut direction = Vector2::new(0.0, 0.0);
-        let bullet_speed = 150.0;
         for (player_position, _player) in (&position, &player).join() {
             player_location = Vector2::new(player_position.x, player_position.y);
             let target_location = Vector2::new(self.mouse_location.x, self.mouse_location.y);
             direction = target_location - player_location;
         }
+        // there's no way to aim with the mouse right on the player
+        if direction.magnitude() == 0.0 {
+            return;
+        }
+        let direction = direction.normalize();
         for (bullet_position, _bullet, bullet_velocity, bullet_state) in
             (&mut position, &bullet, &mut velocity, &mut bullet_state).join()
         {
             if let CurrentBulletState::Ready = bullet_state.get() {
                 if delay_firing_until_after.get() < self.duration_since_start {
-                    direction = direction.normalize();
                     bullet_position.x = player_location.x;
                     bullet_position.y = player_location.y;
-                    bullet_velocity.x = direction.x * bullet_speed;
-                    bullet_velocity.y = direction.y * bullet_speed;
+                    bullet_velocity.x = direction.x * config.bullet_speed;
+                    bullet_velocity.y = direction.y * config.bullet_speed;
                     bullet_state.fire();
-                    delay_firing_until_after.set(self.duration_since_start + 100);
+                    run_stats.shots_fired += 1;
+                    delay_firing_until_after.set(self.duration_since_start + cooldown);
                 }
             }
         }
     }
 }
 
-pub struct ResetBulletsSystem {
-    pub arena_width: f32,
-    pub arena_height: f32,
-}
+pub struct ResetBulletsSystem;
 
 impl<'a> System<'a> for ResetBulletsSystem {
     type SystemData = (
@@ -403,16 +969,17 @@ impl<'a> System<'a> for ResetBulletsSystem {
         WriteStorage<'a, Velocity>,
         ReadStorage<'a, Bullet>,
         WriteStorage<'a, BulletState>,
+        Read<'a, ArenaBounds>,
     );
 
-    fn run(&mut self, (position, mut velocity, bullet, mut bullet_state): Self::SystemData) {
-        for (position, velocity, bullet, bullet_state) in
+    fn run(&mut self, (position, mut velocity, bullet, mut bullet_state, arena): Self::SystemData) {
+        for (position, velocity, _bullet, bullet_state) in
             (&position, &mut velocity, &bullet, &mut bullet_state).join()
         {
             if position.x < -10.0
-                || position.x > self.arena_width + 10.0
+                || position.x > arena.width() + 10.0
                 || position.y < -10.0
-                || position.y > self.arena_height + 10.0
+                || position.y > arena.height() + 10.0
             {
                 bullet_state.ready();
                 velocity.x = 0.0;
@@ -422,38 +989,162 @@ impl<'a> System<'a> for ResetBulletsSystem {
     }
 }
 
-pub struct ShootBirdsSystem;
+pub struct ShootBirdsSystem<'a> {
+    pub duration_since_start: u128,
+    pub collisions: &'a mut ReaderId<Collision>,
+    /// Birds already killed this tick. Deleted birds stay joinable until the world is
+    /// maintained, so this is shared with `ClearScreenSystem` to avoid scoring any twice.
+    pub killed: &'a mut BitSet,
+}
 
-impl<'a> System<'a> for ShootBirdsSystem {
+impl ShootBirdsSystem<'_> {
+    fn drop_power_up(
+        &self,
+        (x, y): (f32, f32),
+        entities: &Entities,
+        seeded_rng: &mut SeededRng,
+        config: &Config,
+        lazy_update: &LazyUpdate,
+    ) {
+        if !seeded_rng.rng().gen_bool(config.power_up_drop_chance) {
+            return;
+        }
+
+        let power_up = *PowerUp::ALL.choose(seeded_rng.rng()).unwrap();
+        lazy_update
+            .create_entity(entities)
+            .with(Position { x, y })
+            .with(ObjectMesh::new(MeshType::PowerUp(power_up)))
+            .with(Velocity { x: 0.0, y: 0.0 })
+            .with(Acceleration { x: 0.0, y: 0.0 })
+            .with(HasGravity)
+            .with(OnGround::new())
+            .with(Height::new(POWER_UP_SIZE / 2.0))
+            .with(Collider::rectangle(
+                POWER_UP_SIZE / 2.0,
+                POWER_UP_SIZE / 2.0,
+                Layers::PICKUP,
+                Layers::PLAYER,
+            ))
+            .with(Pickup {
+                power_up,
+                expires_at: self.duration_since_start + config.pickup_lifetime,
+            })
+            .build();
+    }
+}
+
+impl<'a> System<'a> for ShootBirdsSystem<'a> {
     type SystemData = (
         ReadStorage<'a, Position>,
-        ReadStorage<'a, Bullet>,
         ReadStorage<'a, Flyer>,
         Entities<'a>,
         WriteStorage<'a, BulletState>,
+        WriteStorage<'a, Explosion>,
+        ReadStorage<'a, BirdSpecies>,
+        WriteStorage<'a, HitPoints>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, OnGround>,
+        ReadStorage<'a, CarryingEgg>,
         Write<'a, Score>,
+        Write<'a, RunStats>,
+        Write<'a, SeededRng>,
+        Read<'a, Config>,
+        Read<'a, LazyUpdate>,
+        Read<'a, EventChannel<Collision>>,
     );
 
     fn run(
         &mut self,
-        (mut position, bullet, flyer, entities, mut bullet_state, mut score): Self::SystemData,
+        (
+            position,
+            flyer,
+            entities,
+            mut bullet_state,
+            mut explosion,
+            bird_species,
+            mut hit_points,
+            player,
+            on_ground,
+            carrying_egg,
+            mut score,
+            mut run_stats,
+            mut seeded_rng,
+            config,
+            lazy_update,
+            collisions,
+        ): Self::SystemData,
     ) {
-        for (bullet_position, bullet, bullet_entity, bullet_state) in
-            (&position, &bullet, &entities, &mut bullet_state).join()
-        {
-            if let CurrentBulletState::Firing = bullet_state.get() {
-                let bullet_location = Vector2::new(bullet_position.x, bullet_position.y);
-                for (flyer_position, flyer, flyer_entity) in (&position, &flyer, &entities).join() {
-                    let flyer_location = Vector2::new(flyer_position.x, flyer_position.y);
-                    let direction = flyer_location - bullet_location;
-                    let distance = direction.magnitude();
-                    if distance < 25.0 {
-                        entities.delete(flyer_entity).unwrap();
-                        bullet_state.hit();
-                        score.increase(10);
+        let mid_air = (&player, &on_ground)
+            .join()
+            .any(|(_player, on_ground)| !on_ground.get());
+        for collision in collisions.read(self.collisions) {
+            let bird = collision.other;
+            if self.killed.contains(bird.id()) || !flyer.contains(bird) {
+                continue;
+            }
+            let (bird_position, bird_species) = match (position.get(bird), bird_species.get(bird)) {
+                (Some(bird_position), Some(bird_species)) => (bird_position, bird_species),
+                _ => continue,
+            };
+
+            let blast_kills = if let Some(bullet_state) = bullet_state.get_mut(collision.entity) {
+                // a bullet only ever hits one bird
+                if let CurrentBulletState::Firing = bullet_state.get() {
+                    bullet_state.hit();
+                    if !hit_points.get_mut(bird).is_some_and(HitPoints::hit) {
+                        continue;
                     }
+                    0
+                } else {
+                    continue;
                 }
+            } else if let Some(explosion) = explosion.get_mut(collision.entity) {
+                explosion.kills += 1;
+                explosion.kills
+            } else {
+                continue;
+            };
+
+            let multiplier = score.multiplier(&config);
+            let points = score.kill(
+                &Kill {
+                    points: config.species(bird_species.get()).score,
+                    blast_kills,
+                    mid_air,
+                    carrying_egg: carrying_egg.contains(bird),
+                },
+                self.duration_since_start,
+                &config,
+            );
+            build_popup(
+                lazy_update.create_entity(&entities),
+                (bird_position.x, bird_position.y),
+                format!("+{}", points),
+                POINTS_COLOR,
+                self.duration_since_start,
+                &config,
+            );
+            if score.multiplier(&config) > multiplier {
+                build_popup(
+                    lazy_update.create_entity(&entities),
+                    (bird_position.x, bird_position.y - POPUP_SPACING),
+                    format!("x{} COMBO", score.multiplier(&config)),
+                    COMBO_COLOR,
+                    self.duration_since_start,
+                    &config,
+                );
             }
+            self.killed.add(bird.id());
+            entities.delete(bird).unwrap();
+            run_stats.birds_killed += 1;
+            self.drop_power_up(
+                (bird_position.x, bird_position.y),
+                &entities,
+                &mut seeded_rng,
+                &config,
+                &lazy_update,
+            );
         }
     }
 }
@@ -468,7 +1159,8 @@ impl<'a> System<'a> for HideHitBullets {
     );
 
     fn run(&mut self, (bullet, mut bullet_state, mut position): Self::SystemData) {
-        for (bullet, bullet_state, position) in (&bullet, &mut bullet_state, &mut position).join() {
+        for (_bullet, bullet_state, position) in (&bullet, &mut bullet_state, &mut position).join()
+        {
             if let CurrentBulletState::Hit = bullet_state.get() {
                 bullet_state.ready();
                 position.x = -50.0;
@@ -485,7 +1177,666 @@ impl<'a> System<'a> for IncreaseScoreBySurvivingSystem {
 
     fn run(&mut self, (still_alive, mut score): Self::SystemData) {
         if still_alive.get() {
-            score.increase(1);
+            score.add(ScoreCategory::Survival, 1);
+        }
+    }
+}
+
+/// Lets the combo multiplier run down while the player isn't killing anything.
+pub struct DecayComboSystem {
+    pub duration_since_start: u128,
+}
+
+impl<'a> System<'a> for DecayComboSystem {
+    type SystemData = (Write<'a, Score>, Read<'a, Config>);
+
+    fn run(&mut self, (mut score, config): Self::SystemData) {
+        score.decay_combo(self.duration_since_start, &config);
+    }
+}
+
+pub struct DropPoopSystem {
+    pub duration_since_start: u128,
+}
+
+impl<'a> System<'a> for DropPoopSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Velocity>,
+        ReadStorage<'a, Flyer>,
+        WriteStorage<'a, DelayPoopUntilAfter>,
+        Write<'a, SeededRng>,
+        Read<'a, Config>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            position,
+            velocity,
+            flyer,
+            mut delay_poop_until_after,
+            mut seeded_rng,
+            config,
+            lazy_update,
+        ): Self::SystemData,
+    ) {
+        // only drawn when a delay is set, so birds that are waiting don't use up the rng and
+        // shift every roll after them
+        let duration_since_start = self.duration_since_start;
+        let mut next_poop = || {
+            duration_since_start
+                + seeded_rng
+                    .rng()
+                    .gen_range(config.poop_interval_min, config.poop_interval_max + 1)
+        };
+
+        for (bird, bird_position, bird_velocity, _flyer) in
+            (&entities, &position, &velocity, &flyer).join()
+        {
+            match delay_poop_until_after.get_mut(bird) {
+                // a bird that just arrived waits a random time so the flock doesn't poop in
+                // unison
+                None => {
+                    delay_poop_until_after
+                        .insert(bird, DelayPoopUntilAfter::new(next_poop()))
+                        .unwrap();
+                }
+                Some(delay) if delay.get() < self.duration_since_start => {
+                    delay.set(next_poop());
+                    lazy_update
+                        .create_entity(&entities)
+                        .with(Position {
+                            x: bird_position.x,
+                            y: bird_position.y,
+                        })
+                        .with(ObjectMesh::new(MeshType::Poop))
+                        .with(Velocity {
+                            x: bird_velocity.x,
+                            y: bird_velocity.y,
+                        })
+                        .with(Acceleration { x: 0.0, y: 0.0 })
+                        .with(HasGravity)
+                        .with(OnGround::new())
+                        .with(Height::new(POOP_SIZE))
+                        .with(Collider::rectangle(
+                            POOP_SIZE,
+                            POOP_SIZE,
+                            Layers::POOP,
+                            Layers::PLAYER,
+                        ))
+                        .with(Poop)
+                        .build();
+                }
+                Some(_) => (),
+            }
+        }
+    }
+}
+
+pub struct HitByPoopSystem<'a> {
+    pub duration_since_start: u128,
+    pub collisions: &'a mut ReaderId<Collision>,
+}
+
+impl<'a> System<'a> for HitByPoopSystem<'a> {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Poop>,
+        ReadStorage<'a, OnGround>,
+        WriteStorage<'a, StunnedUntil>,
+        Read<'a, Config>,
+        Read<'a, LazyUpdate>,
+        Read<'a, EventChannel<Collision>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            position,
+            poop,
+            on_ground,
+            mut stunned_until,
+            config,
+            lazy_update,
+            collisions,
+        ): Self::SystemData,
+    ) {
+        let mut landed = BitSet::new();
+        for (poop_entity, _poop, on_ground) in (&entities, &poop, &on_ground).join() {
+            if on_ground.get() {
+                landed.add(poop_entity.id());
+                entities.delete(poop_entity).unwrap();
+            }
+        }
+
+        // poop only listens for the player, so any collision is a hit
+        for collision in collisions.read(self.collisions) {
+            if landed.contains(collision.entity.id()) || !poop.contains(collision.entity) {
+                continue;
+            }
+            stunned_until
+                .insert(
+                    collision.other,
+                    StunnedUntil::new(self.duration_since_start + config.stun_duration),
+                )
+                .unwrap();
+            if let Some(player_position) = position.get(collision.other) {
+                build_popup(
+                    lazy_update.create_entity(&entities),
+                    (player_position.x, player_position.y - STUNNED_POPUP_HEIGHT),
+                    String::from("Stunned!"),
+                    STUNNED_COLOR,
+                    self.duration_since_start,
+                    &config,
+                );
+            }
+            landed.add(collision.entity.id());
+            entities.delete(collision.entity).unwrap();
+        }
+    }
+}
+
+pub struct RecoverFromStunSystem {
+    pub duration_since_start: u128,
+}
+
+impl<'a> System<'a> for RecoverFromStunSystem {
+    type SystemData = (Entities<'a>, WriteStorage<'a, StunnedUntil>);
+
+    fn run(&mut self, (entities, mut stunned_until): Self::SystemData) {
+        let recovered: Vec<Entity> = (&entities, &stunned_until)
+            .join()
+            .filter(|(_entity, stunned_until)| stunned_until.get() < self.duration_since_start)
+            .map(|(entity, _stunned_until)| entity)
+            .collect();
+
+        for entity in recovered {
+            stunned_until.remove(entity);
+        }
+    }
+}
+
+pub struct CollectPickupsSystem<'a> {
+    pub duration_since_start: u128,
+    pub collisions: &'a mut ReaderId<Collision>,
+}
+
+impl<'a> System<'a> for CollectPickupsSystem<'a> {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Pickup>,
+        WriteStorage<'a, ActiveEffects>,
+        WriteStorage<'a, Bombs>,
+        Write<'a, BulletPoolSize>,
+        Read<'a, Config>,
+        Read<'a, LazyUpdate>,
+        Read<'a, EventChannel<Collision>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            position,
+            pickup,
+            mut active_effects,
+            mut bombs,
+            mut bullet_pool_size,
+            config,
+            lazy_update,
+            collisions,
+        ): Self::SystemData,
+    ) {
+        let mut gone = BitSet::new();
+        for (pickup_entity, pickup) in (&entities, &pickup).join() {
+            if pickup.expires_at < self.duration_since_start {
+                gone.add(pickup_entity.id());
+                entities.delete(pickup_entity).unwrap();
+            }
+        }
+
+        // pickups only listen for the player, so any collision means it was picked up
+        for collision in collisions.read(self.collisions) {
+            if gone.contains(collision.entity.id()) {
+                continue;
+            }
+            let (pickup, active_effects, bombs) = match (
+                pickup.get(collision.entity),
+                active_effects.get_mut(collision.other),
+                bombs.get_mut(collision.other),
+            ) {
+                (Some(pickup), Some(active_effects), Some(bombs)) => {
+                    (pickup, active_effects, bombs)
+                }
+                _ => continue,
+            };
+
+            match pickup.power_up {
+                PowerUp::ClearScreen => bombs.add(config.max_bombs),
+                PowerUp::ExtraBullet => bullet_pool_size.grow(config.max_bullets),
+                power_up => active_effects.start(
+                    power_up,
+                    self.duration_since_start + config.power_up_duration,
+                ),
+            }
+            if let Some(pickup_position) = position.get(collision.entity) {
+                build_popup(
+                    lazy_update.create_entity(&entities),
+                    (pickup_position.x, pickup_position.y - POPUP_SPACING),
+                    pickup.power_up.name().to_string(),
+                    PICKUP_COLOR,
+                    self.duration_since_start,
+                    &config,
+                );
+            }
+            gone.add(collision.entity.id());
+            entities.delete(collision.entity).unwrap();
+        }
+    }
+}
+
+/// Deletes whatever has outlived its `Lifetime`.
+pub struct ExpireLifetimesSystem {
+    pub duration_since_start: u128,
+}
+
+impl<'a> System<'a> for ExpireLifetimesSystem {
+    type SystemData = (Entities<'a>, ReadStorage<'a, Lifetime>);
+
+    fn run(&mut self, (entities, lifetime): Self::SystemData) {
+        for (entity, lifetime) in (&entities, &lifetime).join() {
+            if lifetime.is_over(self.duration_since_start) {
+                entities.delete(entity).unwrap();
+            }
+        }
+    }
+}
+
+pub struct ExpireEffectsSystem {
+    pub duration_since_start: u128,
+}
+
+impl<'a> System<'a> for ExpireEffectsSystem {
+    type SystemData = WriteStorage<'a, ActiveEffects>;
+
+    fn run(&mut self, mut active_effects: Self::SystemData) {
+        for active_effects in (&mut active_effects).join() {
+            active_effects.expire(self.duration_since_start);
+        }
+    }
+}
+
+/// Spawns bullets until there are as many as `BulletPoolSize` allows, plus the temporary
+/// ones the machine gun adds while it is running. Temporary bullets are only taken away
+/// once they are back in the pool, never while still in flight.
+pub struct BulletPoolSystem;
+
+impl<'a> System<'a> for BulletPoolSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, ActiveEffects>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, ObjectMesh>,
+        WriteStorage<'a, Velocity>,
+        WriteStorage<'a, Bullet>,
+        WriteStorage<'a, Collider>,
+        WriteStorage<'a, BulletState>,
+        WriteStorage<'a, TemporaryBullet>,
+        Read<'a, BulletPoolSize>,
+        Read<'a, Config>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            active_effects,
+            mut position,
+            mut mesh,
+            mut velocity,
+            mut bullet,
+            mut collider,
+            mut bullet_state,
+            mut temporary_bullet,
+            bullet_pool_size,
+            config,
+        ): Self::SystemData,
+    ) {
+        let machine_gun_active = active_effects
+            .join()
+            .any(|active_effects| active_effects.is_active(PowerUp::MachineGun));
+        let temporary_wanted = if machine_gun_active {
+            config.machine_gun_extra_bullets
+        } else {
+            0
+        };
+        let permanent_count = (&bullet, !&temporary_bullet).join().count();
+        let temporary_count = temporary_bullet.join().count();
+
+        let permanent_missing = bullet_pool_size.get().saturating_sub(permanent_count);
+        let temporary_missing = temporary_wanted.saturating_sub(temporary_count);
+        for index in 0..permanent_missing + temporary_missing {
+            let builder = entities
+                .build_entity()
+                .with(Position { x: -50.0, y: -50.0 }, &mut position)
+                .with(ObjectMesh::new(MeshType::Bullet), &mut mesh)
+                .with(Velocity { x: 0.0, y: 0.0 }, &mut velocity)
+                .with(Bullet, &mut bullet)
+                // a bullet hits wherever its centre goes, the bird's collider has the slack
+                .with(
+                    Collider::circle(0.0, Layers::BULLET, Layers::BIRD),
+                    &mut collider,
+                )
+                .with(BulletState::new(), &mut bullet_state);
+            if index < permanent_missing {
+                builder.build();
+            } else {
+                builder.with(TemporaryBullet, &mut temporary_bullet).build();
+            }
+        }
+
+        let mut surplus = temporary_count.saturating_sub(temporary_wanted);
+        for (entity, bullet_state, _temporary_bullet) in
+            (&entities, &bullet_state, &temporary_bullet).join()
+        {
+            if surplus == 0 {
+                break;
+            }
+            if let CurrentBulletState::Ready = bullet_state.get() {
+                entities.delete(entity).unwrap();
+                surplus -= 1;
+            }
+        }
+    }
+}
+
+pub struct LaunchGrenadeSystem {
+    pub mouse_location: Point2<f32>,
+    pub duration_since_start: u128,
+}
+
+impl<'a> System<'a> for LaunchGrenadeSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, StunnedUntil>,
+        ReadStorage<'a, ActiveEffects>,
+        Write<'a, DelayFiringUntilAfter>,
+        Write<'a, RunStats>,
+        Read<'a, Config>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            position,
+            player,
+            stunned_until,
+            active_effects,
+            mut delay_firing_until_after,
+            mut run_stats,
+            config,
+            lazy_update,
+        ): Self::SystemData,
+    ) {
+        if delay_firing_until_after.get() >= self.duration_since_start {
+            return;
+        }
+
+        for (player_position, _player, active_effects, _stunned_until) in
+            (&position, &player, &active_effects, !&stunned_until).join()
+        {
+            if !active_effects.is_active(PowerUp::GrenadeLauncher) {
+                continue;
+            }
+
+            let player_location = Vector2::new(player_position.x, player_position.y);
+            let target_location = Vector2::new(self.mouse_location.x, self.mouse_location.y);
+            let direction = target_location - player_location;
+            if direction.magnitude() == 0.0 {
+                continue;
+            }
+            let direction = direction.normalize();
+
+            lazy_update
+                .create_entity(&entities)
+                .with(Position {
+                    x: player_location.x,
+                    y: player_location.y,
+                })
+                .with(ObjectMesh::new(MeshType::Grenade))
+                .with(Velocity {
+                    x: direction.x * config.grenade_speed,
+                    y: direction.y * config.grenade_speed,
+                })
+                .with(Acceleration { x: 0.0, y: 0.0 })
+                .with(HasGravity)
+                .with(OnGround::new())
+                .with(Height::new(GRENADE_SIZE))
+                .with(KeepInBounds::new(EdgeBehaviour::Bounce))
+                .with(Collider::circle(0.0, Layers::GRENADE, Layers::BIRD))
+                .with(Grenade)
+                .build();
+            run_stats.shots_fired += 1;
+            delay_firing_until_after.set(self.duration_since_start + config.grenade_cooldown);
+        }
+    }
+}
+
+/// Turns grenades that have landed or hit a bird into explosions.
+pub struct DetonateGrenadesSystem<'a> {
+    pub duration_since_start: u128,
+    pub collisions: &'a mut ReaderId<Collision>,
+}
+
+impl<'a> System<'a> for DetonateGrenadesSystem<'a> {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Grenade>,
+        ReadStorage<'a, OnGround>,
+        Read<'a, Config>,
+        Read<'a, LazyUpdate>,
+        Read<'a, EventChannel<Collision>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, position, grenade, on_ground, config, lazy_update, collisions): Self::SystemData,
+    ) {
+        // grenades only listen for birds, so any collision means one was hit
+        let mut hit_bird = BitSet::new();
+        for collision in collisions.read(self.collisions) {
+            hit_bird.add(collision.entity.id());
+        }
+
+        for (grenade_entity, grenade_position, _grenade, on_ground) in
+            (&entities, &position, &grenade, &on_ground).join()
+        {
+            let hit_bird = hit_bird.contains(grenade_entity.id());
+            if on_ground.get() || hit_bird {
+                entities.delete(grenade_entity).unwrap();
+                lazy_update
+                    .create_entity(&entities)
+                    .with(Position {
+                        x: grenade_position.x,
+                        y: grenade_position.y,
+                    })
+                    .with(ObjectMesh::new(MeshType::Explosion))
+                    .with(Collider::circle(0.0, Layers::EXPLOSION, Layers::BIRD))
+                    .with(Explosion::new(
+                        config.grenade_blast_radius,
+                        self.duration_since_start,
+                        self.duration_since_start + EXPLOSION_DURATION,
+                    ))
+                    .build();
+            }
+        }
+    }
+}
+
+pub struct UpdateExplosionsSystem {
+    pub duration_since_start: u128,
+}
+
+impl<'a> System<'a> for UpdateExplosionsSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Explosion>,
+        WriteStorage<'a, Collider>,
+    );
+
+    fn run(&mut self, (entities, mut explosion, mut collider): Self::SystemData) {
+        for (explosion_entity, explosion, collider) in
+            (&entities, &mut explosion, &mut collider).join()
+        {
+            if explosion.ends_at <= self.duration_since_start {
+                entities.delete(explosion_entity).unwrap();
+                continue;
+            }
+
+            let progress = (self.duration_since_start - explosion.started_at) as f32
+                / (explosion.ends_at - explosion.started_at) as f32;
+            explosion.radius = explosion.max_radius * progress.min(1.0);
+            collider.shape = Shape::Circle {
+                radius: explosion.radius,
+            };
+        }
+    }
+}
+
+/// Sets off one of the player's bombs, killing every bird in the arena. The egg and the
+/// player are left alone, and a carried egg simply drops.
+pub struct ClearScreenSystem<'a> {
+    pub duration_since_start: u128,
+    /// Birds already killed this tick, see `ShootBirdsSystem`.
+    pub killed: &'a mut BitSet,
+}
+
+impl<'a> System<'a> for ClearScreenSystem<'a> {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, StunnedUntil>,
+        WriteStorage<'a, Bombs>,
+        ReadStorage<'a, Flyer>,
+        ReadStorage<'a, BirdSpecies>,
+        Write<'a, Score>,
+        Write<'a, RunStats>,
+        Read<'a, Config>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            position,
+            player,
+            stunned_until,
+            mut bombs,
+            flyer,
+            bird_species,
+            mut score,
+            mut run_stats,
+            config,
+            lazy_update,
+        ): Self::SystemData,
+    ) {
+        for (_player, bombs, _stunned_until) in (&player, &mut bombs, !&stunned_until).join() {
+            if !bombs.take() {
+                continue;
+            }
+
+            let mut bombed = BitSet::new();
+            for (bird, bird_position, _flyer, bird_species, _alive) in
+                (&entities, &position, &flyer, &bird_species, !&*self.killed).join()
+            {
+                bombed.add(bird.id());
+                entities.delete(bird).unwrap();
+                // a bomb takes no skill, so it doesn't count towards a combo
+                let points = config.species(bird_species.get()).score;
+                score.add(ScoreCategory::Kills, points);
+                run_stats.birds_killed += 1;
+                build_popup(
+                    lazy_update.create_entity(&entities),
+                    (bird_position.x, bird_position.y),
+                    format!("+{}", points),
+                    POINTS_COLOR,
+                    self.duration_since_start,
+                    &config,
+                );
+            }
+            *self.killed |= &bombed;
+        }
+    }
+}
+
+/// Picks how fast each bird beats its wings from how fast it is flying. Diving birds tuck
+/// their wings in and birds hanging over the egg hold them spread.
+pub struct FlapWingsSystem;
+
+impl<'a> System<'a> for FlapWingsSystem {
+    type SystemData = (
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Velocity>,
+        ReadStorage<'a, Flyer>,
+        ReadStorage<'a, KeepAlive>,
+        WriteStorage<'a, Animation>,
+    );
+
+    fn run(&mut self, (position, velocity, flyer, keep_alive, mut animation): Self::SystemData) {
+        let egg_locations: Vec<Vector2<f32>> = (&position, &keep_alive)
+            .join()
+            .map(|(egg_position, _keep_alive)| Vector2::new(egg_position.x, egg_position.y))
+            .collect();
+
+        for (bird_position, bird_velocity, _flyer, animation) in
+            (&position, &velocity, &flyer, &mut animation).join()
+        {
+            let bird_location = Vector2::new(bird_position.x, bird_position.y);
+            let speed = Vector2::new(bird_velocity.x, bird_velocity.y).magnitude();
+            let diving =
+                bird_velocity.y > DIVE_SPEED && bird_velocity.y > bird_velocity.x.abs() * 1.5;
+            let hovering = speed < HOVER_SPEED
+                && egg_locations.iter().any(|egg_location| {
+                    (egg_location - bird_location).magnitude() < HOVER_DISTANCE
+                });
+
+            animation.set_speed(FLAP_RATE + speed * FLAP_RATE_PER_SPEED);
+            if diving {
+                animation.hold(Some(MeshType::Bird(WingPose::Tucked)));
+            } else if hovering {
+                animation.hold(Some(MeshType::Bird(WingPose::Level)));
+            } else {
+                animation.hold(None);
+            }
+        }
+    }
+}
+
+pub struct AnimationSystem {
+    pub delta_time: f32,
+}
+
+impl<'a> System<'a> for AnimationSystem {
+    type SystemData = (WriteStorage<'a, Animation>, WriteStorage<'a, ObjectMesh>);
+
+    fn run(&mut self, (mut animation, mut mesh): Self::SystemData) {
+        for (animation, mesh) in (&mut animation, &mut mesh).join() {
+            animation.advance(self.delta_time);
+            mesh.set(animation.current());
         }
     }
 }
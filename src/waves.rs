@@ -0,0 +1,339 @@
+use super::config::{milliseconds, Config};
+use super::species::Species;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Which side of the arena a bird flies in from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpawnEdge {
+    Top,
+    Left,
+    Right,
+}
+
+impl SpawnEdge {
+    pub const ALL: [SpawnEdge; 3] = [SpawnEdge::Top, SpawnEdge::Left, SpawnEdge::Right];
+}
+
+/// A hand-written wave from the config file. Scripted waves are played first, in order,
+/// and the director makes up the rest once they run out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WaveSpec {
+    /// How many of each species the wave sends.
+    #[serde(default)]
+    pub pigeon: usize,
+    #[serde(default)]
+    pub hawk: usize,
+    #[serde(default)]
+    pub goose: usize,
+    /// Each bird comes in from one of these, picked at random.
+    #[serde(default = "top_only")]
+    pub edges: Vec<SpawnEdge>,
+    /// Milliseconds between one bird and the next.
+    #[serde(with = "milliseconds")]
+    pub spawn_interval: u128,
+    /// Milliseconds of calm after the last bird of the wave is gone.
+    #[serde(with = "milliseconds")]
+    pub pause_after: u128,
+}
+
+impl WaveSpec {
+    pub fn count(&self, species: Species) -> usize {
+        match species {
+            Species::Pigeon => self.pigeon,
+            Species::Hawk => self.hawk,
+            Species::Goose => self.goose,
+        }
+    }
+}
+
+fn top_only() -> Vec<SpawnEdge> {
+    vec![SpawnEdge::Top]
+}
+
+/// One wave, planned out in full when it starts. Its timing is looked up in the config
+/// every tick instead, so a reloaded config changes the pace of the wave already underway.
+#[derive(Debug, Clone)]
+struct Wave {
+    number: usize,
+    birds: Vec<Species>,
+    edges: Vec<SpawnEdge>,
+}
+
+impl Wave {
+    /// Takes the scripted wave for `number` if there is one, otherwise each wave is
+    /// `wave_size_growth` birds bigger than the last and birds start coming in from the
+    /// sides once `side_spawns_from_wave` is reached.
+    fn plan(
+        number: usize,
+        duration_since_start: u128,
+        config: &Config,
+        rng: &mut impl Rng,
+    ) -> Wave {
+        if let Some(spec) = config.waves.get(number - 1) {
+            let mut birds: Vec<Species> = Species::ALL
+                .iter()
+                .flat_map(|species| vec![*species; spec.count(*species)])
+                .collect();
+            birds.shuffle(rng);
+            return Wave {
+                number,
+                birds,
+                edges: spec.edges.clone(),
+            };
+        }
+
+        let size = (config.first_wave_size + (number - 1) * config.wave_size_growth)
+            .min(config.max_birds_per_wave);
+        let edges = if number >= config.side_spawns_from_wave {
+            SpawnEdge::ALL.to_vec()
+        } else {
+            top_only()
+        };
+        Wave {
+            number,
+            birds: (0..size)
+                .map(|_| Species::choose(rng, duration_since_start, config))
+                .collect(),
+            edges,
+        }
+    }
+
+    fn spawn_interval(&self, config: &Config) -> u128 {
+        config
+            .waves
+            .get(self.number - 1)
+            .map_or(config.wave_spawn_interval, |spec| spec.spawn_interval)
+    }
+
+    fn pause_after(&self, config: &Config) -> u128 {
+        config
+            .waves
+            .get(self.number - 1)
+            .map_or(config.wave_pause, |spec| spec.pause_after)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WavePhase {
+    /// Between waves, waiting until the given time.
+    Resting { until: u128 },
+    /// Still sending birds, the last one at the given time or none yet.
+    Spawning { last_spawn_at: Option<u128> },
+    /// Every bird is out, waiting for the player to finish them off.
+    Fighting,
+}
+
+/// What the director wants done this tick.
+#[derive(Debug, Default)]
+pub struct WaveUpdate {
+    pub spawns: Vec<(Species, SpawnEdge)>,
+    /// The number of the wave that was just cleared, if one was.
+    pub cleared: Option<usize>,
+}
+
+/// Decides when birds arrive. Sends waves of birds one after another, each with a short
+/// pause once the last wave has been shot down.
+#[derive(Debug)]
+pub struct WaveDirector {
+    number: usize,
+    wave: Option<Wave>,
+    phase: WavePhase,
+}
+
+impl WaveDirector {
+    pub fn new() -> WaveDirector {
+        WaveDirector {
+            number: 0,
+            wave: None,
+            phase: WavePhase::Resting { until: 0 },
+        }
+    }
+
+    /// The wave being fought, or the last one cleared while resting. 0 before the first.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// When the next wave starts, if the director is between waves.
+    pub fn next_wave_at(&self) -> Option<u128> {
+        match self.phase {
+            WavePhase::Resting { until } => Some(until),
+            _ => None,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        duration_since_start: u128,
+        birds_alive: usize,
+        config: &Config,
+        rng: &mut impl Rng,
+    ) -> WaveUpdate {
+        let mut update = WaveUpdate::default();
+
+        if let WavePhase::Resting { until } = self.phase {
+            if until > duration_since_start {
+                return update;
+            }
+            self.number += 1;
+            self.wave = Some(Wave::plan(self.number, duration_since_start, config, rng));
+            self.phase = WavePhase::Spawning {
+                last_spawn_at: None,
+            };
+        }
+
+        let wave = match &mut self.wave {
+            Some(wave) => wave,
+            None => return update,
+        };
+
+        if let WavePhase::Spawning { mut last_spawn_at } = self.phase {
+            let spawn_interval = wave.spawn_interval(config).max(1);
+            let mut next_spawn_at =
+                last_spawn_at.map_or(duration_since_start, |last| last + spawn_interval);
+            while next_spawn_at <= duration_since_start {
+                match wave.birds.pop() {
+                    Some(species) => {
+                        let edge = *wave.edges.choose(rng).unwrap_or(&SpawnEdge::Top);
+                        update.spawns.push((species, edge));
+                        last_spawn_at = Some(next_spawn_at);
+                        next_spawn_at += spawn_interval;
+                    }
+                    None => break,
+                }
+            }
+            self.phase = if wave.birds.is_empty() {
+                WavePhase::Fighting
+            } else {
+                WavePhase::Spawning { last_spawn_at }
+            };
+        }
+
+        // the birds from this tick haven't been created yet, so they aren't counted
+        if self.phase == WavePhase::Fighting && birds_alive == 0 && update.spawns.is_empty() {
+            self.phase = WavePhase::Resting {
+                until: duration_since_start + wave.pause_after(config),
+            };
+            update.cleared = Some(self.number);
+        }
+
+        update
+    }
+}
+
+impl Default for WaveDirector {
+    fn default() -> WaveDirector {
+        WaveDirector::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn config() -> Config {
+        let mut config = Config::new();
+        config.waves = vec![WaveSpec {
+            pigeon: 2,
+            hawk: 0,
+            goose: 0,
+            edges: vec![SpawnEdge::Left],
+            spawn_interval: 100,
+            pause_after: 500,
+        }];
+        config.first_wave_size = 3;
+        config.wave_size_growth = 2;
+        config.wave_spawn_interval = 100;
+        config.wave_pause = 1000;
+        config.side_spawns_from_wave = 3;
+        config
+    }
+
+    #[test]
+    fn plays_the_scripted_wave_then_rests() {
+        let config = config();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut director = WaveDirector::new();
+
+        let update = director.update(0, 0, &config, &mut rng);
+        assert_eq!(director.number(), 1);
+        assert_eq!(update.spawns, vec![(Species::Pigeon, SpawnEdge::Left)]);
+        assert!(director.update(50, 1, &config, &mut rng).spawns.is_empty());
+        let update = director.update(100, 1, &config, &mut rng);
+        assert_eq!(update.spawns, vec![(Species::Pigeon, SpawnEdge::Left)]);
+
+        // not over while either bird is still flying
+        let update = director.update(200, 1, &config, &mut rng);
+        assert!(update.spawns.is_empty());
+        assert_eq!(update.cleared, None);
+        assert_eq!(director.next_wave_at(), None);
+
+        let update = director.update(300, 0, &config, &mut rng);
+        assert_eq!(update.cleared, Some(1));
+        assert_eq!(director.next_wave_at(), Some(800));
+        assert!(director.update(799, 0, &config, &mut rng).spawns.is_empty());
+        assert_eq!(director.number(), 1);
+    }
+
+    #[test]
+    fn makes_up_bigger_waves_once_the_script_runs_out() {
+        let config = config();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut director = WaveDirector::new();
+        let mut now = 0;
+        let mut spawned_per_wave = vec![];
+        let mut edges_per_wave = vec![];
+
+        while director.number() < 4 || director.next_wave_at().is_none() {
+            let update = director.update(now, 0, &config, &mut rng);
+            if update.cleared.is_none() && !update.spawns.is_empty() {
+                if spawned_per_wave.len() < director.number() {
+                    spawned_per_wave.push(0);
+                    edges_per_wave.push(vec![]);
+                }
+                spawned_per_wave[director.number() - 1] += update.spawns.len();
+                edges_per_wave[director.number() - 1]
+                    .extend(update.spawns.iter().map(|(_species, edge)| *edge));
+            }
+            now += 50;
+        }
+
+        // made up waves still count the scripted ones when working out their size
+        assert_eq!(spawned_per_wave, vec![2, 5, 7, 9]);
+        assert!(edges_per_wave[1].iter().all(|edge| *edge == SpawnEdge::Top));
+        assert!(edges_per_wave[3].iter().any(|edge| *edge != SpawnEdge::Top));
+    }
+
+    #[test]
+    fn a_late_update_sends_every_bird_it_missed() {
+        let mut config = config();
+        config.waves.clear();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut director = WaveDirector::new();
+
+        assert_eq!(director.update(0, 0, &config, &mut rng).spawns.len(), 1);
+        assert_eq!(director.update(250, 1, &config, &mut rng).spawns.len(), 2);
+        assert!(director.update(300, 3, &config, &mut rng).spawns.is_empty());
+    }
+
+    #[test]
+    fn a_new_spawn_interval_applies_to_the_wave_underway() {
+        let mut config = config();
+        config.waves.clear();
+        config.wave_spawn_interval = 1000;
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut director = WaveDirector::new();
+        assert_eq!(director.update(0, 0, &config, &mut rng).spawns.len(), 1);
+
+        config.wave_spawn_interval = 100;
+        assert_eq!(director.update(100, 1, &config, &mut rng).spawns.len(), 1);
+        assert!(director.update(150, 2, &config, &mut rng).spawns.is_empty());
+        assert_eq!(director.update(200, 2, &config, &mut rng).spawns.len(), 1);
+    }
+}
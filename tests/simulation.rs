@@ -0,0 +1,512 @@
+use keep_it_alive::components::{
+    Acceleration, ActiveEffects, BirdSpecies, Bombs, Bullet, BulletState, Collider, Explosion,
+    Flyer, Grenade, Height, Layers, OnGround, Pickup, Player, Poop, Position, PowerUp,
+    StunnedUntil, Velocity, Width,
+};
+use keep_it_alive::resources::{ArenaBounds, GameOverReason};
+use keep_it_alive::{Config, Input, Replay, Simulation, TICKS_PER_SECOND};
+use specs::prelude::*;
+
+fn bird_count(simulation: &Simulation) -> usize {
+    simulation.world().read_storage::<Flyer>().join().count()
+}
+
+fn on_screen_bird(simulation: &Simulation) -> Option<Entity> {
+    let world = simulation.world();
+    let arena = simulation.arena();
+    let position = world.read_storage::<Position>();
+    let flyer = world.read_storage::<Flyer>();
+    (&world.entities(), &position, &flyer)
+        .join()
+        .find(|(_bird, position, _flyer)| {
+            position.x > 50.0
+                && position.x < arena.width() - 50.0
+                && position.y > 50.0
+                && position.y < arena.height() - 50.0
+        })
+        .map(|(bird, _position, _flyer)| bird)
+}
+
+/// Sends a first wave of `birds` all at once, with no poop to stun the player.
+fn calm_config(birds: usize) -> Config {
+    let mut config = Config::new();
+    config.waves.clear();
+    config.wave_spawn_interval = 1;
+    config.first_wave_size = birds;
+    config.poop_interval_min = 1_000_000;
+    config.poop_interval_max = 1_000_000;
+    config
+}
+
+/// The player starts in the air, this gives them time to land.
+fn wait_for_the_player_to_land(simulation: &mut Simulation) {
+    for _ in 0..3 * TICKS_PER_SECOND {
+        simulation.tick(&Input::new());
+    }
+}
+
+fn wait_for_birds(simulation: &mut Simulation, birds: usize) {
+    while bird_count(simulation) < birds {
+        simulation.tick(&Input::new());
+    }
+}
+
+fn bombs(simulation: &Simulation) -> usize {
+    let world = simulation.world();
+    let bombs = world.read_storage::<Bombs>();
+    bombs.join().map(|bombs| bombs.get()).sum()
+}
+
+fn give_bombs(simulation: &Simulation, count: usize) {
+    for bombs in (&mut simulation.world().write_storage::<Bombs>()).join() {
+        for _ in 0..count {
+            bombs.add(count);
+        }
+    }
+}
+
+fn player_position(simulation: &Simulation) -> (f32, f32) {
+    let world = simulation.world();
+    let position = world.read_storage::<Position>();
+    let player = world.read_storage::<Player>();
+    let (position, _player) = (&position, &player).join().next().unwrap();
+    (position.x, position.y)
+}
+
+/// Holds a bird still at `(x, y)`, since left alone it heads for the egg.
+fn pin(simulation: &Simulation, bird: Entity, (x, y): (f32, f32)) {
+    let world = simulation.world();
+    if let Some(position) = world.write_storage::<Position>().get_mut(bird) {
+        *position = Position { x, y };
+    }
+    if let Some(velocity) = world.write_storage::<Velocity>().get_mut(bird) {
+        *velocity = Velocity { x: 0.0, y: 0.0 };
+    }
+    if let Some(acceleration) = world.write_storage::<Acceleration>().get_mut(bird) {
+        *acceleration = Acceleration { x: 0.0, y: 0.0 };
+    }
+}
+
+fn has_effect(simulation: &Simulation, power_up: PowerUp) -> bool {
+    let world = simulation.world();
+    let active_effects = world.read_storage::<ActiveEffects>();
+    let player = world.read_storage::<Player>();
+    (&active_effects, &player)
+        .join()
+        .any(|(active_effects, _player)| active_effects.is_active(power_up))
+}
+
+/// Puts a pickup right where the player is standing.
+fn drop_pickup_on_player(simulation: &Simulation, power_up: PowerUp, expires_at: u128) -> Entity {
+    let (x, y) = player_position(simulation);
+    let world = simulation.world();
+    let pickup = world.entities().create();
+    world
+        .write_storage::<Position>()
+        .insert(pickup, Position { x, y })
+        .unwrap();
+    world
+        .write_storage::<Collider>()
+        .insert(
+            pickup,
+            Collider::rectangle(5.0, 5.0, Layers::PICKUP, Layers::PLAYER),
+        )
+        .unwrap();
+    world
+        .write_storage::<Pickup>()
+        .insert(
+            pickup,
+            Pickup {
+                power_up,
+                expires_at,
+            },
+        )
+        .unwrap();
+    pickup
+}
+
+/// Plays until the run is over, giving up after `seconds`.
+fn play(simulation: &mut Simulation, seconds: u32, mut input_for_tick: impl FnMut(usize) -> Input) {
+    for tick in 0..(seconds * TICKS_PER_SECOND) as usize {
+        if !simulation.still_alive() {
+            return;
+        }
+        simulation.tick(&input_for_tick(tick));
+    }
+}
+
+#[test]
+fn a_player_who_does_nothing_has_the_egg_stolen() {
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, Config::new());
+    play(&mut simulation, 120, |_tick| Input::new());
+
+    let game_over = simulation.game_over().expect("the run should have ended");
+    assert_eq!(game_over.reason, GameOverReason::EggStolen);
+    assert!(game_over.species.is_some());
+    assert_eq!(simulation.stats().birds_killed, 0);
+}
+
+#[test]
+fn a_replay_plays_out_the_same_game() {
+    let arena = ArenaBounds::default();
+    let mut simulation = Simulation::new(arena, 7, Config::new());
+    let mut replay = Replay::new(7, arena, Config::new());
+    // walk back and forth shooting at the sky so birds die and power ups drop
+    play(&mut simulation, 60, |tick| {
+        let mut input = Input::new();
+        input.move_left = tick / 90 % 2 == 0;
+        input.move_right = !input.move_left;
+        input.firing = tick % 20 < 10;
+        input.mouse_location.x = (tick * 7 % 800) as f32;
+        input.mouse_location.y = 100.0;
+        replay.record(&input);
+        input
+    });
+    assert!(simulation.stats().birds_killed > 0);
+
+    let mut replayed = Simulation::new(replay.arena(), replay.seed(), replay.config().clone());
+    play(&mut replayed, 60, |tick| {
+        replay.input_for_tick(tick).cloned().unwrap_or_default()
+    });
+
+    assert_eq!(replayed.game_over(), simulation.game_over());
+    assert_eq!(replayed.score(), simulation.score());
+    assert_eq!(
+        replayed.stats().birds_killed,
+        simulation.stats().birds_killed
+    );
+    assert_eq!(replayed.stats().shots_fired, simulation.stats().shots_fired);
+    assert_eq!(
+        replayed.duration_since_start(),
+        simulation.duration_since_start()
+    );
+}
+
+#[test]
+fn a_bird_shot_as_a_bomb_goes_off_is_only_counted_once() {
+    let mut config = Config::new();
+    // the first wave comes in a bird a tick, so none turn up on the bomb's tick
+    config.waves.clear();
+    config.wave_spawn_interval = 1;
+    let wave_size = config.first_wave_size;
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, config);
+    // and bullets off the screen are taken back, so wait for one to fly into view
+    while bird_count(&simulation) < wave_size || on_screen_bird(&simulation).is_none() {
+        simulation.tick(&Input::new());
+    }
+    let birds = bird_count(&simulation);
+    let bird = on_screen_bird(&simulation).unwrap();
+
+    {
+        let world = simulation.world();
+        let entities = world.entities();
+        let bullet = world.read_storage::<Bullet>();
+        let mut position = world.write_storage::<Position>();
+        let mut velocity = world.write_storage::<Velocity>();
+        let mut bullet_state = world.write_storage::<BulletState>();
+
+        let bird_position = position.get(bird).unwrap();
+        let shot_position = Position {
+            x: bird_position.x,
+            y: bird_position.y,
+        };
+        // keep up with the bird so the bullet is still on it when collisions are checked
+        let bird_velocity = velocity.get(bird).unwrap();
+        let shot_velocity = Velocity {
+            x: bird_velocity.x,
+            y: bird_velocity.y,
+        };
+        let shot = (&entities, &bullet).join().next().unwrap().0;
+        position.insert(shot, shot_position).unwrap();
+        velocity.insert(shot, shot_velocity).unwrap();
+        bullet_state.get_mut(shot).unwrap().fire();
+        for bombs in (&mut world.write_storage::<Bombs>()).join() {
+            bombs.add(1);
+        }
+    }
+
+    let mut input = Input::new();
+    input.bomb = true;
+    simulation.tick(&input);
+
+    assert_eq!(bird_count(&simulation), 0);
+    assert_eq!(simulation.stats().birds_killed, birds);
+}
+
+#[test]
+fn a_player_standing_still_stays_on_the_ground() {
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, Config::new());
+    wait_for_the_player_to_land(&mut simulation);
+
+    for _ in 0..TICKS_PER_SECOND {
+        simulation.tick(&Input::new());
+        let world = simulation.world();
+        let player = world.read_storage::<Player>();
+        let on_ground = world.read_storage::<OnGround>();
+        for (_player, on_ground) in (&player, &on_ground).join() {
+            assert!(on_ground.get());
+        }
+    }
+}
+
+#[test]
+fn firing_with_the_mouse_on_the_player_does_nothing() {
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, Config::new());
+    wait_for_the_player_to_land(&mut simulation);
+    let (player_x, player_y) = {
+        let world = simulation.world();
+        let position = world.read_storage::<Position>();
+        let player = world.read_storage::<Player>();
+        let (position, _player) = (&position, &player).join().next().unwrap();
+        (position.x, position.y)
+    };
+
+    let mut input = Input::new();
+    input.firing = true;
+    input.mouse_location.x = player_x;
+    input.mouse_location.y = player_y;
+    simulation.tick(&input);
+
+    assert_eq!(simulation.stats().shots_fired, 0);
+    let world = simulation.world();
+    let position = world.read_storage::<Position>();
+    let velocity = world.read_storage::<Velocity>();
+    let bullet = world.read_storage::<Bullet>();
+    for (position, velocity, _bullet) in (&position, &velocity, &bullet).join() {
+        assert!(position.x.is_finite() && position.y.is_finite());
+        assert!(velocity.x.is_finite() && velocity.y.is_finite());
+    }
+}
+
+#[test]
+fn reloading_a_new_bird_size_resizes_birds_already_flying() {
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, Config::new());
+    while bird_count(&simulation) == 0 {
+        simulation.tick(&Input::new());
+    }
+
+    let mut config = simulation.config();
+    config.bird_width *= 2.0;
+    config.bird_height *= 3.0;
+    simulation.set_config(config.clone());
+
+    let world = simulation.world();
+    let bird_species = world.read_storage::<BirdSpecies>();
+    let width = world.read_storage::<Width>();
+    let height = world.read_storage::<Height>();
+    for (bird_species, width, height) in (&bird_species, &width, &height).join() {
+        let size = config.species(bird_species.get()).size;
+        assert_eq!(width.get(), config.bird_width * size);
+        assert_eq!(height.get(), config.bird_height * size);
+    }
+}
+
+#[test]
+fn a_bomb_is_used_up_and_kills_every_bird() {
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, calm_config(3));
+    wait_for_birds(&mut simulation, 3);
+    give_bombs(&simulation, 2);
+
+    let mut input = Input::new();
+    input.bomb = true;
+    simulation.tick(&input);
+
+    assert_eq!(bombs(&simulation), 1);
+    assert_eq!(bird_count(&simulation), 0);
+    assert_eq!(simulation.stats().birds_killed, 3);
+}
+
+#[test]
+fn holding_the_bomb_key_only_sets_off_one_bomb() {
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, calm_config(3));
+    wait_for_birds(&mut simulation, 3);
+    give_bombs(&simulation, 3);
+
+    let mut input = Input::new();
+    input.bomb = true;
+    for _ in 0..10 {
+        simulation.tick(&input);
+    }
+    assert_eq!(bombs(&simulation), 2);
+
+    simulation.tick(&Input::new());
+    assert_eq!(bombs(&simulation), 2);
+    simulation.tick(&input);
+    assert_eq!(bombs(&simulation), 1);
+}
+
+#[test]
+fn the_bomb_key_does_nothing_without_bombs() {
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, calm_config(3));
+    wait_for_birds(&mut simulation, 3);
+
+    let mut input = Input::new();
+    input.bomb = true;
+    simulation.tick(&input);
+
+    assert_eq!(bombs(&simulation), 0);
+    assert_eq!(bird_count(&simulation), 3);
+}
+
+#[test]
+fn a_grenade_explodes_on_landing_and_kills_the_birds_in_its_blast() {
+    let config = calm_config(2);
+    let blast_radius = config.grenade_blast_radius;
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, config);
+    wait_for_the_player_to_land(&mut simulation);
+    wait_for_birds(&mut simulation, 2);
+    let birds: Vec<Entity> = {
+        let world = simulation.world();
+        let flyer = world.read_storage::<Flyer>();
+        (&world.entities(), &flyer)
+            .join()
+            .map(|(bird, _flyer)| bird)
+            .collect()
+    };
+    let (player_x, player_y) = player_position(&simulation);
+    let floor = simulation.arena().height();
+    let inside = (player_x + blast_radius / 2.0, floor - 30.0);
+    let outside = (player_x + blast_radius * 3.0, floor - 30.0);
+    for active_effects in (&mut simulation.world().write_storage::<ActiveEffects>()).join() {
+        active_effects.start(PowerUp::GrenadeLauncher, u128::MAX);
+    }
+
+    // throw one grenade straight down at the floor
+    let mut input = Input::new();
+    input.firing = true;
+    input.mouse_location.x = player_x;
+    input.mouse_location.y = player_y + 100.0;
+    pin(&simulation, birds[0], inside);
+    pin(&simulation, birds[1], outside);
+    simulation.tick(&input);
+    assert_eq!(simulation.stats().shots_fired, 1);
+
+    let mut ticks_in_flight = 0;
+    loop {
+        pin(&simulation, birds[0], inside);
+        pin(&simulation, birds[1], outside);
+        simulation.tick(&Input::new());
+        let world = simulation.world();
+        if world.read_storage::<Grenade>().join().next().is_none() {
+            break;
+        }
+        ticks_in_flight += 1;
+        assert!(world.read_storage::<Explosion>().join().next().is_none());
+        assert!(ticks_in_flight < TICKS_PER_SECOND);
+    }
+    assert!(ticks_in_flight > 0);
+    assert!(simulation
+        .world()
+        .read_storage::<Explosion>()
+        .join()
+        .next()
+        .is_some());
+
+    // let the blast grow to its full size and burn out
+    for _ in 0..TICKS_PER_SECOND {
+        pin(&simulation, birds[0], inside);
+        pin(&simulation, birds[1], outside);
+        simulation.tick(&Input::new());
+    }
+    let world = simulation.world();
+    assert!(world.read_storage::<Explosion>().join().next().is_none());
+    let flyer = world.read_storage::<Flyer>();
+    assert!(!flyer.contains(birds[0]));
+    assert!(flyer.contains(birds[1]));
+    assert_eq!(simulation.stats().birds_killed, 1);
+}
+
+#[test]
+fn a_pickup_touched_in_time_starts_its_effect() {
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, calm_config(1));
+    wait_for_the_player_to_land(&mut simulation);
+    let expires_at = simulation.duration_since_start() + 1000;
+    let pickup = drop_pickup_on_player(&simulation, PowerUp::MachineGun, expires_at);
+
+    simulation.tick(&Input::new());
+
+    assert!(has_effect(&simulation, PowerUp::MachineGun));
+    assert!(!simulation.world().is_alive(pickup));
+}
+
+#[test]
+fn a_pickup_that_runs_out_as_it_is_touched_is_lost() {
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, calm_config(1));
+    wait_for_the_player_to_land(&mut simulation);
+    // gone by the time the next tick comes round
+    let expires_at = simulation.duration_since_start();
+    let pickup = drop_pickup_on_player(&simulation, PowerUp::MachineGun, expires_at);
+
+    simulation.tick(&Input::new());
+
+    assert!(!has_effect(&simulation, PowerUp::MachineGun));
+    assert!(!simulation.world().is_alive(pickup));
+}
+
+#[test]
+fn an_effect_wears_off_after_its_duration() {
+    let config = calm_config(1);
+    let power_up_duration = config.power_up_duration;
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, config);
+    wait_for_the_player_to_land(&mut simulation);
+    let expires_at = simulation.duration_since_start() + 1000;
+    drop_pickup_on_player(&simulation, PowerUp::GrenadeLauncher, expires_at);
+    simulation.tick(&Input::new());
+    let ends_at = simulation.duration_since_start() + power_up_duration;
+
+    while simulation.duration_since_start() < ends_at {
+        assert!(has_effect(&simulation, PowerUp::GrenadeLauncher));
+        simulation.tick(&Input::new());
+    }
+    assert!(!has_effect(&simulation, PowerUp::GrenadeLauncher));
+}
+
+#[test]
+fn poop_stuns_the_player_for_the_stun_duration() {
+    let config = calm_config(1);
+    let stun_duration = config.stun_duration;
+    let mut simulation = Simulation::new(ArenaBounds::default(), 1, config);
+    wait_for_the_player_to_land(&mut simulation);
+    let (x, y) = player_position(&simulation);
+    {
+        let world = simulation.world();
+        let poop = world.entities().create();
+        world
+            .write_storage::<Position>()
+            .insert(poop, Position { x, y })
+            .unwrap();
+        world
+            .write_storage::<Collider>()
+            .insert(
+                poop,
+                Collider::rectangle(4.0, 4.0, Layers::POOP, Layers::PLAYER),
+            )
+            .unwrap();
+        world.write_storage::<Poop>().insert(poop, Poop).unwrap();
+    }
+    let stunned = |simulation: &Simulation| {
+        let world = simulation.world();
+        let player = world.read_storage::<Player>();
+        let stunned_until = world.read_storage::<StunnedUntil>();
+        (&player, &stunned_until).join().next().is_some()
+    };
+
+    simulation.tick(&Input::new());
+    assert!(stunned(&simulation));
+    let recovers_after = simulation.duration_since_start() + stun_duration;
+
+    // shooting at the sky does nothing until the stun wears off
+    let mut input = Input::new();
+    input.firing = true;
+    input.mouse_location.x = x;
+    loop {
+        simulation.tick(&input);
+        if simulation.duration_since_start() > recovers_after {
+            break;
+        }
+        assert!(stunned(&simulation));
+        assert_eq!(simulation.stats().shots_fired, 0);
+    }
+    assert!(!stunned(&simulation));
+    assert_eq!(simulation.stats().shots_fired, 1);
+}